@@ -47,7 +47,8 @@ async fn root() -> impl IntoResponse {
 }
 
 async fn protected(jar: CookieJar) -> impl IntoResponse {
-    let jwt_cookie = match jar.get("jwt") {
+    // The auth service may be configured to use a `__Host-` prefixed cookie
+    let jwt_cookie = match jar.get("__Host-jwt").or_else(|| jar.get("jwt")) {
         Some(cookie) => cookie,
         None => {
             return StatusCode::UNAUTHORIZED.into_response();
@@ -58,6 +59,7 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
 
     let verify_token_body = serde_json::json!({
         "token": &jwt_cookie.value(),
+        "audience": "app-service",
     });
 
     let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
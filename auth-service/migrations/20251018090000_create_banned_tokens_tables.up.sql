@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS banned_tokens(
+    jti TEXT NOT NULL PRIMARY KEY,
+    expires_at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS token_revocations(
+    subject TEXT NOT NULL PRIMARY KEY,
+    revoked_at BIGINT NOT NULL
+);
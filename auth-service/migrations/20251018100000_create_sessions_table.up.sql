@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS sessions(
+    id TEXT NOT NULL PRIMARY KEY,
+    email TEXT NOT NULL,
+    device TEXT,
+    ip TEXT,
+    user_agent TEXT,
+    created_at BIGINT NOT NULL,
+    last_seen_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS sessions_email_idx ON sessions (email);
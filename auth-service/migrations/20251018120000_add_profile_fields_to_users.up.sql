@@ -0,0 +1,4 @@
+-- Accounts created before this column existed get the time of the migration
+ALTER TABLE users ADD COLUMN IF NOT EXISTS created_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS last_login_at BIGINT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS display_name TEXT;
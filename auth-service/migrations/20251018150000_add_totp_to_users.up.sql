@@ -0,0 +1,6 @@
+ALTER TABLE users ADD COLUMN IF NOT EXISTS two_fa_method TEXT NOT NULL DEFAULT 'email'
+    CHECK (two_fa_method IN ('email', 'totp'));
+-- Both secrets are encrypted by the application, bound to the id of the user
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_secret TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS pending_totp_secret TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_last_used_step BIGINT;
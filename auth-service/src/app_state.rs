@@ -2,20 +2,29 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::domain::{
-    data_stores::{BannedTokenStore, TwoFACodeStore, UserStore},
+    data_stores::{
+        BannedTokenStore, LoginAttemptStore, RefreshTokenStore, SessionStore, TwoFACodeStore,
+        UserStore,
+    },
     EmailClient,
 };
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore + Send + Sync>>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
+pub type LoginAttemptStoreType = Arc<RwLock<dyn LoginAttemptStore + Send + Sync>>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub session_store: SessionStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub login_attempt_store: LoginAttemptStoreType,
     pub email_client: EmailClientType,
 }
 
@@ -23,13 +32,19 @@ impl AppState {
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
+        refresh_token_store: RefreshTokenStoreType,
+        session_store: SessionStoreType,
         two_fa_code_store: TwoFACodeStoreType,
+        login_attempt_store: LoginAttemptStoreType,
         email_client: EmailClientType,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
+            refresh_token_store,
+            session_store,
             two_fa_code_store,
+            login_attempt_store,
             email_client,
         }
     }
@@ -1,7 +1,11 @@
+use std::hash::Hash;
+
 use color_eyre::eyre::{eyre, Context, Report, Result};
+use rand::{distributions::Alphanumeric, Rng};
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use thiserror::Error;
+use uuid::Uuid;
 
 use crate::domain::{email::Email, password::Password};
 
@@ -9,16 +13,106 @@ use super::user::User;
 
 #[async_trait::async_trait]
 pub trait UserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    // The recovery codes are stored together with the user, so a user who
+    // signs up with 2FA never exists without them
+    async fn add_user(
+        &mut self,
+        user: User,
+        recovery_code_hashes: Vec<String>,
+    ) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    async fn record_login(
+        &mut self,
+        email: &Email,
+        logged_in_at: u64,
+    ) -> Result<(), UserStoreError>;
+    // Saves the user's editable fields. The password only changes through
+    // `update_password` so that it is always stored hashed, and the email only
+    // through `update_email`. Every update except a login moves `updated_at`.
+    async fn update_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    async fn update_email(&mut self, email: &Email, new_email: Email)
+        -> Result<(), UserStoreError>;
+    // Recovery codes are only ever stored as hashes. Replacing them with an
+    // empty list removes them all.
+    async fn replace_recovery_codes(
+        &mut self,
+        user_id: Uuid,
+        code_hashes: Vec<String>,
+    ) -> Result<(), UserStoreError>;
+    // Removes the code so it cannot be used again. Fails with
+    // `InvalidCredentials` if the user has no such code.
+    async fn use_recovery_code(
+        &mut self,
+        user_id: Uuid,
+        code_hash: &str,
+    ) -> Result<(), UserStoreError>;
+    async fn count_recovery_codes(&self, user_id: Uuid) -> Result<usize, UserStoreError>;
 }
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
-    async fn contains_token(&self, token: Secret<String>) -> Result<bool, BannedTokenStoreError>;
+    async fn add_token(
+        &mut self,
+        jti: String,
+        expires_at: u64,
+    ) -> Result<(), BannedTokenStoreError>;
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError>;
+    async fn revoke_all_tokens(
+        &mut self,
+        subject: &str,
+        revoked_at: u64,
+    ) -> Result<(), BannedTokenStoreError>;
+    async fn get_revocation_epoch(
+        &self,
+        subject: &str,
+    ) -> Result<Option<u64>, BannedTokenStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait RefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn get_token(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError>;
+    async fn mark_token_used(&mut self, token: &RefreshToken)
+        -> Result<(), RefreshTokenStoreError>;
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait SessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError>;
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    async fn touch_session(&mut self, id: &str, last_seen_at: u64)
+        -> Result<(), SessionStoreError>;
+    async fn delete_session(&mut self, id: &str) -> Result<(), SessionStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait LoginAttemptStore {
+    // Returns no failures for an email that has none recorded
+    async fn get_attempts(&self, email: &Email) -> Result<LoginAttempts, LoginAttemptStoreError>;
+    async fn set_attempts(
+        &mut self,
+        email: &Email,
+        attempts: LoginAttempts,
+    ) -> Result<(), LoginAttemptStoreError>;
+    async fn remove_attempts(&mut self, email: &Email) -> Result<(), LoginAttemptStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -60,25 +154,118 @@ impl PartialEq for BannedTokenStoreError {
     }
 }
 
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum LoginAttemptStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for LoginAttemptStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Pending 2FA challenges are keyed by login attempt, so a user can have
+// several logins waiting on their codes at once.
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
+    // The audience is the one the login asked for, which the token issued
+    // once the code is verified must be for
     async fn add_code(
         &mut self,
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
+        audience: String,
+    ) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    // Removes every pending challenge for the user, so none of them can finish
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    // Returns the email the challenge was issued for, so callers can check it
+    // belongs to the user trying to complete it
     async fn get_code(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode), TwoFACodeStoreError>;
+    async fn get_codes(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    ) -> Result<Vec<(LoginAttemptId, TwoFACode)>, TwoFACodeStoreError>;
+    async fn get_audience(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<String, TwoFACodeStoreError>;
+    // Counts a wrong code against the login attempt. The miss that reaches
+    // `max_attempts` removes the code and fails with `TooManyFailedAttempts`,
+    // so the user has to log in again to get a new one.
+    async fn record_failed_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError>;
+    // Returns the code to send again. A resend is refused within
+    // `cooldown_seconds` of the last send, and after `max_resends` of them.
+    async fn record_resend(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        cooldown_seconds: u64,
+        max_resends: u32,
+    ) -> Result<TwoFACode, TwoFACodeStoreError>;
 }
 
 #[derive(Debug, Error)]
 pub enum TwoFACodeStoreError {
     #[error("Login attempt id not found")]
     LoginAttemptIdNotFound,
+    #[error("Too many failed attempts")]
+    TooManyFailedAttempts,
+    #[error("Code resent too recently")]
+    ResendTooSoon,
+    #[error("Too many resends")]
+    TooManyResends,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -88,6 +275,9 @@ impl PartialEq for TwoFACodeStoreError {
         matches!(
             (self, other),
             (Self::LoginAttemptIdNotFound, Self::LoginAttemptIdNotFound)
+                | (Self::TooManyFailedAttempts, Self::TooManyFailedAttempts)
+                | (Self::ResendTooSoon, Self::ResendTooSoon)
+                | (Self::TooManyResends, Self::TooManyResends)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -102,10 +292,18 @@ impl PartialEq for LoginAttemptId {
     }
 }
 
+impl Hash for LoginAttemptId {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.expose_secret().hash(state)
+    }
+}
+
+impl Eq for LoginAttemptId {}
+
 impl LoginAttemptId {
     pub fn parse(s: Secret<String>) -> Result<Self> {
         let parsed_id =
-            uuid::Uuid::parse_str(&s.expose_secret()).wrap_err("Invalid login attempt id")?;
+            uuid::Uuid::parse_str(s.expose_secret()).wrap_err("Invalid login attempt id")?;
 
         Ok(Self(Secret::new(parsed_id.to_string())))
     }
@@ -146,3 +344,209 @@ impl AsRef<Secret<String>> for TwoFACode {
         &self.0
     }
 }
+
+#[derive(Clone, Debug)]
+pub struct RefreshToken(Secret<String>);
+
+impl PartialEq for RefreshToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl RefreshToken {
+    pub fn parse(s: Secret<String>) -> Result<Self> {
+        let token = s.expose_secret();
+
+        if token.len() == REFRESH_TOKEN_LENGTH && token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            Ok(Self(s))
+        } else {
+            Err(eyre!("Invalid refresh token"))
+        }
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(REFRESH_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+
+        Self(Secret::new(token))
+    }
+}
+
+impl AsRef<Secret<String>> for RefreshToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+const REFRESH_TOKEN_LENGTH: usize = 64;
+
+// Every refresh token issued from the same login shares a family id, so that
+// presenting an already used token can revoke the whole chain of rotations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefreshTokenRecord {
+    pub email: Email,
+    pub audience: String,
+    pub family_id: String,
+    pub authenticated_at: u64,
+    pub used: bool,
+}
+
+impl RefreshTokenRecord {
+    pub fn new(email: Email, audience: String, family_id: String, authenticated_at: u64) -> Self {
+        Self {
+            email,
+            audience,
+            family_id,
+            authenticated_at,
+            used: false,
+        }
+    }
+}
+
+// One login on one device. Its id is carried in the `sid` claim of every JWT
+// issued for it, and doubles as the id of its refresh token family.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub email: Email,
+    pub device: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: u64,
+    pub last_seen_at: u64,
+}
+
+impl Session {
+    pub fn new(
+        email: Email,
+        device: Option<String>,
+        ip: Option<String>,
+        user_agent: Option<String>,
+        created_at: u64,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            email,
+            device,
+            ip,
+            user_agent,
+            created_at,
+            last_seen_at: created_at,
+        }
+    }
+}
+
+// Failed logins for one email. Once they reach the threshold, every further
+// failure locks the account for twice as long as the one before it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoginAttempts {
+    pub failures: u32,
+    pub last_failed_at: u64,
+    pub locked_until: Option<u64>,
+}
+
+impl LoginAttempts {
+    pub fn is_locked(&self, now: u64) -> bool {
+        self.locked_until
+            .is_some_and(|locked_until| now < locked_until)
+    }
+
+    // Returns whether this failure locked the account
+    pub fn record_failure(&mut self, now: u64, threshold: u32, lockout_seconds: u64) -> bool {
+        if now >= self.expires_at() {
+            *self = Self::default();
+        }
+
+        self.failures += 1;
+        self.last_failed_at = now;
+
+        if self.failures < threshold {
+            return false;
+        }
+
+        let doublings = (self.failures - threshold).min(MAX_LOCKOUT_DOUBLINGS);
+        let lockout = lockout_seconds
+            .saturating_mul(1 << doublings)
+            .min(MAX_LOCKOUT_SECONDS);
+        self.locked_until = Some(now + lockout);
+
+        true
+    }
+
+    // Failures are forgotten once a day has passed since the last one, or
+    // since the lock it caused ended
+    pub fn expires_at(&self) -> u64 {
+        self.locked_until
+            .unwrap_or_default()
+            .max(self.last_failed_at)
+            .saturating_add(FAILED_LOGIN_WINDOW_SECONDS)
+    }
+}
+
+const FAILED_LOGIN_WINDOW_SECONDS: u64 = 60 * 60 * 24; // 1 day
+const MAX_LOCKOUT_SECONDS: u64 = 60 * 60 * 24; // 1 day
+const MAX_LOCKOUT_DOUBLINGS: u32 = 16;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_failure_should_lock_at_threshold() {
+        let mut attempts = LoginAttempts::default();
+
+        for now in 1..3 {
+            assert!(!attempts.record_failure(now, 3, 60));
+            assert!(!attempts.is_locked(now));
+        }
+
+        assert!(attempts.record_failure(3, 3, 60));
+        assert_eq!(attempts.locked_until, Some(63));
+        assert!(attempts.is_locked(62));
+        assert!(!attempts.is_locked(63));
+    }
+
+    #[test]
+    fn record_failure_should_double_lockout_after_threshold() {
+        let mut attempts = LoginAttempts::default();
+
+        for now in 1..=3 {
+            attempts.record_failure(now, 3, 60);
+        }
+
+        assert!(attempts.record_failure(100, 3, 60));
+        assert_eq!(attempts.locked_until, Some(220));
+
+        assert!(attempts.record_failure(300, 3, 60));
+        assert_eq!(attempts.locked_until, Some(540));
+    }
+
+    #[test]
+    fn record_failure_should_cap_lockout() {
+        let mut attempts = LoginAttempts {
+            failures: 40,
+            last_failed_at: 1,
+            locked_until: None,
+        };
+
+        attempts.record_failure(2, 3, 60);
+        assert_eq!(attempts.locked_until, Some(2 + MAX_LOCKOUT_SECONDS));
+    }
+
+    #[test]
+    fn record_failure_should_forget_old_failures() {
+        let mut attempts = LoginAttempts::default();
+
+        attempts.record_failure(1, 3, 60);
+        attempts.record_failure(2, 3, 60);
+
+        assert!(!attempts.record_failure(2 + FAILED_LOGIN_WINDOW_SECONDS, 3, 60));
+        assert_eq!(attempts.failures, 1);
+    }
+}
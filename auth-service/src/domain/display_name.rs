@@ -0,0 +1,56 @@
+use color_eyre::eyre::{eyre, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisplayName(String);
+
+impl DisplayName {
+    pub fn parse(s: String) -> Result<Self> {
+        let trimmed = s.trim();
+
+        if validate_display_name(trimmed) {
+            Ok(Self(trimmed.to_owned()))
+        } else {
+            Err(eyre!("Failed to parse string to DisplayName type"))
+        }
+    }
+}
+
+fn validate_display_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars().count() <= MAX_DISPLAY_NAME_LENGTH
+        && !s.chars().any(char::is_control)
+}
+
+const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+
+impl AsRef<str> for DisplayName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DisplayName;
+
+    #[test]
+    fn blank_string_is_rejected() {
+        assert!(DisplayName::parse("   ".to_owned()).is_err());
+    }
+
+    #[test]
+    fn string_longer_than_64_characters_is_rejected() {
+        assert!(DisplayName::parse("a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn control_characters_are_rejected() {
+        assert!(DisplayName::parse("Jane\nDoe".to_owned()).is_err());
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_trimmed() {
+        let display_name = DisplayName::parse("  Jane Doe ".to_owned()).unwrap();
+        assert_eq!(display_name.as_ref(), "Jane Doe");
+    }
+}
@@ -13,6 +13,28 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid Token")]
     InvalidToken,
+    #[error("Invalid Audience")]
+    InvalidAudience,
+    #[error("Invalid Client")]
+    InvalidClient,
+    #[error("Untrusted Origin")]
+    UntrustedOrigin,
+    #[error("Session Not Found")]
+    SessionNotFound,
+    #[error("Email Not Verified")]
+    EmailNotVerified,
+    #[error("Account Locked")]
+    AccountLocked,
+    #[error("Too Many 2FA Attempts")]
+    TooMany2FAAttempts,
+    #[error("2FA Resend Too Soon")]
+    Resend2FATooSoon,
+    #[error("Too Many 2FA Resends")]
+    TooMany2FAResends,
+    #[error("Account Inactive")]
+    AccountInactive,
+    #[error("User Not Found")]
+    UserNotFound,
     #[error("Unexpected Error")]
     UnexpectedError(#[source] Report),
 }
@@ -1,11 +1,17 @@
 pub mod data_stores;
+pub mod display_name;
 pub mod email;
 pub mod email_client;
 pub mod error;
 pub mod password;
+pub mod recovery_code;
+pub mod totp;
 pub mod user;
 
+pub use display_name::*;
 pub use email::*;
 pub use email_client::*;
 pub use error::*;
 pub use password::*;
+pub use recovery_code::*;
+pub use totp::*;
@@ -0,0 +1,116 @@
+use color_eyre::eyre::{eyre, Result};
+use rand::Rng;
+use ring::digest::{digest, SHA256};
+use secrecy::{ExposeSecret, Secret};
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+// Lowercase letters and digits without the ones that are easy to misread
+const ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+const CODE_LENGTH: usize = 10;
+
+// A single-use replacement for a 2FA code, for when the user cannot get one.
+// Written as two groups of five, but the dash and case are ignored when parsing.
+#[derive(Clone, Debug)]
+pub struct RecoveryCode(Secret<String>);
+
+impl PartialEq for RecoveryCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl RecoveryCode {
+    pub fn generate() -> Self {
+        let mut rng = rand::thread_rng();
+        let code: String = (0..CODE_LENGTH)
+            .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char)
+            .collect();
+
+        Self(Secret::new(code))
+    }
+
+    pub fn generate_set() -> Vec<Self> {
+        (0..RECOVERY_CODE_COUNT).map(|_| Self::generate()).collect()
+    }
+
+    pub fn parse(s: Secret<String>) -> Result<Self> {
+        let code: String = s
+            .expose_secret()
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        if code.len() == CODE_LENGTH && code.bytes().all(|b| ALPHABET.contains(&b)) {
+            Ok(Self(Secret::new(code)))
+        } else {
+            Err(eyre!("Invalid recovery code"))
+        }
+    }
+
+    // Codes are random enough that a fast hash is safe, and it lets the
+    // store look a code up by its hash instead of checking each one.
+    pub fn hash(&self) -> String {
+        digest(&SHA256, self.0.expose_secret().as_bytes())
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    pub fn formatted(&self) -> Secret<String> {
+        let (first, second) = self.0.expose_secret().split_at(CODE_LENGTH / 2);
+
+        Secret::new(format!("{}-{}", first, second))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_codes_parse_in_their_formatted_form() {
+        for code in RecoveryCode::generate_set() {
+            let formatted = code.formatted();
+
+            assert_eq!(formatted.expose_secret().len(), CODE_LENGTH + 1);
+            assert_eq!(RecoveryCode::parse(formatted).unwrap(), code);
+        }
+    }
+
+    #[test]
+    fn parse_ignores_case_dashes_and_spaces() {
+        let code = RecoveryCode::parse(Secret::new("abcde-fghjk".to_owned())).unwrap();
+
+        for input in ["ABCDE-FGHJK", "abcdefghjk", " abcde fghjk "] {
+            assert_eq!(
+                RecoveryCode::parse(Secret::new(input.to_owned())).unwrap(),
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_codes_are_rejected() {
+        for input in ["", "123456", "abcde-fghj", "abcde-fghjkm", "abcde-fghi1"] {
+            assert!(
+                RecoveryCode::parse(Secret::new(input.to_owned())).is_err(),
+                "Accepted {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn hash_is_stable_and_hides_the_code() {
+        let code = RecoveryCode::parse(Secret::new("abcde-fghjk".to_owned())).unwrap();
+        let same = RecoveryCode::parse(Secret::new("ABCDEFGHJK".to_owned())).unwrap();
+
+        assert_eq!(code.hash(), same.hash());
+        assert_eq!(code.hash().len(), 64);
+        assert!(!code.hash().contains("abcde"));
+        assert_ne!(code.hash(), RecoveryCode::generate().hash());
+    }
+}
@@ -0,0 +1,297 @@
+use color_eyre::eyre::{eyre, Result};
+use rand::RngCore;
+use ring::hmac;
+use secrecy::{ExposeSecret, Secret};
+use subtle::ConstantTimeEq;
+
+use super::Email;
+
+// RFC 6238 with the parameters every authenticator app supports
+const SECRET_LENGTH: usize = 20;
+const PERIOD_SECONDS: u64 = 30;
+const DIGITS: u32 = 6;
+// Accept the previous and next code too, to allow for clock drift
+const ALLOWED_DRIFT_STEPS: u64 = 1;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// Kept in its base32 form, which is what users type into authenticator apps
+#[derive(Clone, Debug)]
+pub struct TotpSecret(Secret<String>);
+
+impl PartialEq for TotpSecret {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+// A code from an authenticator app. Unlike emailed codes these can start
+// with zeros, so they are kept as the six digits the user typed.
+#[derive(Clone, Debug)]
+pub struct TotpCode(Secret<String>);
+
+impl PartialEq for TotpCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl TotpCode {
+    pub fn parse(s: Secret<String>) -> Result<Self> {
+        let code = s.expose_secret();
+
+        if code.len() == DIGITS as usize && code.bytes().all(|b| b.is_ascii_digit()) {
+            Ok(Self(s))
+        } else {
+            Err(eyre!("Invalid TOTP code"))
+        }
+    }
+}
+
+impl AsRef<Secret<String>> for TotpCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+impl TotpSecret {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; SECRET_LENGTH];
+        rand::thread_rng().fill_bytes(&mut bytes);
+
+        Self(Secret::new(base32_encode(&bytes)))
+    }
+
+    pub fn parse(s: Secret<String>) -> Result<Self> {
+        let normalized = s.expose_secret().trim().to_ascii_uppercase();
+
+        match base32_decode(&normalized) {
+            Some(bytes) if !bytes.is_empty() => Ok(Self(Secret::new(normalized))),
+            _ => Err(eyre!("Invalid TOTP secret")),
+        }
+    }
+
+    // The URI authenticator apps import, usually by scanning it as a QR code
+    pub fn provisioning_uri(&self, issuer: &str, email: &Email) -> Secret<String> {
+        Secret::new(format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            percent_encode(issuer),
+            percent_encode(email.as_ref().expose_secret()),
+            self.0.expose_secret(),
+            percent_encode(issuer),
+            DIGITS,
+            PERIOD_SECONDS
+        ))
+    }
+
+    // Returns the time step the code belongs to. Codes from `last_used_step`
+    // or earlier are refused, so an intercepted code cannot be replayed.
+    pub fn verify(&self, code: &TotpCode, now: u64, last_used_step: Option<u64>) -> Option<u64> {
+        let current_step = now / PERIOD_SECONDS;
+        let first_step = current_step.saturating_sub(ALLOWED_DRIFT_STEPS);
+
+        (first_step..=current_step + ALLOWED_DRIFT_STEPS)
+            .filter(|step| last_used_step.is_none_or(|last| *step > last))
+            .find(|step| {
+                self.code_at(*step)
+                    .as_bytes()
+                    .ct_eq(code.as_ref().expose_secret().as_bytes())
+                    .into()
+            })
+    }
+
+    // The code an authenticator app shows at `time`
+    pub fn generate_code(&self, time: u64) -> TotpCode {
+        TotpCode(Secret::new(self.code_at(time / PERIOD_SECONDS)))
+    }
+
+    fn code_at(&self, step: u64) -> String {
+        let key_bytes = base32_decode(self.0.expose_secret()).unwrap_or_default();
+        let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, &key_bytes);
+        let digest = hmac::sign(&key, &step.to_be_bytes());
+        let digest = digest.as_ref();
+
+        // Dynamic truncation from RFC 4226
+        let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            digest[offset] & 0x7f,
+            digest[offset + 1],
+            digest[offset + 2],
+            digest[offset + 3],
+        ]);
+
+        format!(
+            "{:0width$}",
+            binary % 10u32.pow(DIGITS),
+            width = DIGITS as usize
+        )
+    }
+}
+
+impl AsRef<Secret<String>> for TotpSecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer = 0u16;
+    let mut bits = 0;
+
+    for byte in bytes {
+        buffer = (buffer << 8) | u16::from(*byte);
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    encoded
+}
+
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+
+    for c in s.trim_end_matches('=').bytes() {
+        let value = BASE32_ALPHABET.iter().position(|a| *a == c)? as u16;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The SHA1 secret from the RFC 6238 test vectors, "12345678901234567890"
+    fn rfc_secret() -> TotpSecret {
+        TotpSecret::parse(Secret::new("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_owned())).unwrap()
+    }
+
+    fn code(s: &str) -> TotpCode {
+        TotpCode::parse(Secret::new(s.to_owned())).unwrap()
+    }
+
+    #[test]
+    fn base32_round_trips() {
+        assert_eq!(
+            base32_encode(b"12345678901234567890"),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+        );
+        assert_eq!(
+            base32_decode("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ").unwrap(),
+            b"12345678901234567890"
+        );
+    }
+
+    #[test]
+    fn matches_rfc_test_vectors() {
+        let secret = rfc_secret();
+
+        for (time, expected) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ] {
+            assert_eq!(secret.code_at(time / PERIOD_SECONDS), expected);
+        }
+    }
+
+    #[test]
+    fn codes_with_leading_zeros_are_generated_and_verified() {
+        let secret = rfc_secret();
+
+        for (time, expected) in [(1111111109, "081804"), (1234567890, "005924")] {
+            assert_eq!(secret.generate_code(time), code(expected));
+            assert_eq!(
+                secret.verify(&code(expected), time, None),
+                Some(time / PERIOD_SECONDS)
+            );
+        }
+    }
+
+    #[test]
+    fn totp_code_must_be_six_digits() {
+        for input in ["000000", "005924", "999999"] {
+            assert!(TotpCode::parse(Secret::new(input.to_owned())).is_ok());
+        }
+
+        for input in ["", "5924", "0059240", "00592a", " 05924", "+05924"] {
+            assert!(
+                TotpCode::parse(Secret::new(input.to_owned())).is_err(),
+                "Accepted {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn verify_allows_one_step_of_drift() {
+        let secret = rfc_secret();
+
+        assert_eq!(secret.verify(&code("287082"), 59, None), Some(1));
+        assert_eq!(secret.verify(&code("287082"), 89, None), Some(1));
+        assert_eq!(secret.verify(&code("287082"), 119, None), None);
+    }
+
+    #[test]
+    fn verify_refuses_used_steps() {
+        let secret = rfc_secret();
+
+        assert_eq!(secret.verify(&code("287082"), 59, Some(1)), None);
+        assert_eq!(secret.verify(&code("287082"), 59, Some(0)), Some(1));
+    }
+
+    #[test]
+    fn generated_secret_parses() {
+        let secret = TotpSecret::generate();
+
+        assert_eq!(secret.as_ref().expose_secret().len(), 32);
+        assert_eq!(TotpSecret::parse(secret.as_ref().clone()).unwrap(), secret);
+    }
+
+    #[test]
+    fn invalid_secret_is_rejected() {
+        assert!(TotpSecret::parse(Secret::new("not base32!".to_owned())).is_err());
+        assert!(TotpSecret::parse(Secret::new(String::new())).is_err());
+    }
+
+    #[test]
+    fn provisioning_uri_encodes_the_label() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let uri = rfc_secret().provisioning_uri("auth-service", &email);
+
+        assert_eq!(
+            uri.expose_secret(),
+            "otpauth://totp/auth-service:test%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=auth-service&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
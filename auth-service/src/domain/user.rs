@@ -1,18 +1,141 @@
-use super::{Email, Password};
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+use uuid::Uuid;
 
+use super::{DisplayName, Email, Password, TotpSecret};
+
+// The id never changes, so it is what other services should refer to a user by
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
+    pub id: Uuid,
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    pub two_fa_method: TwoFAMethod,
+    pub totp_secret: Option<TotpSecret>,
+    pub pending_totp_secret: Option<TotpSecret>,
+    pub totp_last_used_step: Option<u64>,
+    pub email_verified: bool,
+    pub display_name: Option<DisplayName>,
+    pub status: UserStatus,
+    pub status_reason: Option<String>,
+    pub status_changed_at: Option<u64>,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub last_login_at: Option<u64>,
 }
 
 impl User {
+    // New users have not proven they own their email address yet
     pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
+        let created_at = Utc::now().timestamp().try_into().unwrap_or_default();
+
         Self {
+            id: Uuid::new_v4(),
             email,
             password,
             requires_2fa,
+            two_fa_method: TwoFAMethod::Email,
+            totp_secret: None,
+            pending_totp_secret: None,
+            totp_last_used_step: None,
+            email_verified: false,
+            display_name: None,
+            status: UserStatus::Active,
+            status_reason: None,
+            status_changed_at: None,
+            created_at,
+            updated_at: created_at,
+            last_login_at: None,
         }
     }
 }
+
+// Only active users can log in. Suspension is meant to be temporary, while a
+// disabled account is not expected to come back.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum UserStatus {
+    #[default]
+    Active,
+    Suspended,
+    Disabled,
+}
+
+impl UserStatus {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "active" => Ok(Self::Active),
+            "suspended" => Ok(Self::Suspended),
+            "disabled" => Ok(Self::Disabled),
+            _ => Err(eyre!("{} is not a valid user status", s)),
+        }
+    }
+}
+
+impl AsRef<str> for UserStatus {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Active => "active",
+            Self::Suspended => "suspended",
+            Self::Disabled => "disabled",
+        }
+    }
+}
+
+// How the second factor is proven once 2FA is enabled. TOTP only becomes the
+// method once an authenticator app has been enrolled and confirmed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TwoFAMethod {
+    #[default]
+    Email,
+    Totp,
+}
+
+impl TwoFAMethod {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "email" => Ok(Self::Email),
+            "totp" => Ok(Self::Totp),
+            _ => Err(eyre!("{} is not a valid 2FA method", s)),
+        }
+    }
+}
+
+impl AsRef<str> for TwoFAMethod {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Email => "email",
+            Self::Totp => "totp",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TwoFAMethod, UserStatus};
+
+    #[test]
+    fn status_round_trips_through_str() {
+        for status in [
+            UserStatus::Active,
+            UserStatus::Suspended,
+            UserStatus::Disabled,
+        ] {
+            assert_eq!(UserStatus::parse(status.as_ref()).unwrap(), status);
+        }
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        assert!(UserStatus::parse("banned").is_err());
+    }
+
+    #[test]
+    fn two_fa_method_round_trips_through_str() {
+        for method in [TwoFAMethod::Email, TwoFAMethod::Totp] {
+            assert_eq!(TwoFAMethod::parse(method.as_ref()).unwrap(), method);
+        }
+
+        assert!(TwoFAMethod::parse("sms").is_err());
+    }
+}
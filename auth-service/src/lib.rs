@@ -1,9 +1,11 @@
-use std::error::Error;
+use std::{error::Error, net::SocketAddr};
 
 use axum::{
-    http::{Method, StatusCode},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    http::{HeaderValue, Method, StatusCode},
+    middleware::{self, AddExtension},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post},
     serve::Serve,
     Json, Router,
 };
@@ -17,7 +19,11 @@ use app_state::AppState;
 
 use crate::{
     domain::error::AuthAPIError,
-    utils::tracing::{make_span_with_request_id, on_request, on_response},
+    utils::{
+        constants::ALLOWED_ORIGINS,
+        csrf,
+        tracing::{make_span_with_request_id, on_request, on_response},
+    },
 };
 
 pub mod app_state;
@@ -28,7 +34,10 @@ pub mod utils;
 
 // This struct encapsulates our application-related logic
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     // address is exposed as a public field
     // so we have access to it in tests.
     pub address: String,
@@ -36,13 +45,13 @@ pub struct Application {
 
 impl Application {
     pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
-        let allowed_origins = [
-            "http://localhost:8000".parse()?,
-            "https://lgr.wallys.world".parse()?,
-        ];
+        let allowed_origins = ALLOWED_ORIGINS
+            .iter()
+            .map(|origin| origin.parse())
+            .collect::<Result<Vec<HeaderValue>, _>>()?;
 
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
@@ -51,9 +60,48 @@ impl Application {
             .route("/signup", post(routes::signup))
             .route("/login", post(routes::login))
             .route("/logout", post(routes::logout))
+            .route("/logout-all", post(routes::logout_all))
             .route("/verify-2fa", post(routes::verify_2fa))
+            .route("/resend-2fa", post(routes::resend_2fa))
             .route("/verify-token", post(routes::verify_token))
+            .route("/refresh", post(routes::refresh))
+            .route("/introspect", post(routes::introspect))
+            .route(
+                "/verify-email",
+                get(routes::verify_email_page).post(routes::verify_email),
+            )
+            .route("/resend-verification", post(routes::resend_verification))
+            .route(
+                "/unlock-account",
+                get(routes::unlock_account_page).post(routes::unlock_account),
+            )
+            .route("/forgot-password", post(routes::forgot_password))
+            .route("/reset-password", post(routes::reset_password))
+            .route("/change-password", post(routes::change_password))
+            .route("/change-email", post(routes::change_email))
+            .route(
+                "/confirm-email-change",
+                get(routes::email_change_page).post(routes::confirm_email_change),
+            )
+            .route(
+                "/undo-email-change",
+                get(routes::email_change_page).post(routes::undo_email_change),
+            )
+            .route("/settings/2fa", post(routes::update_2fa_settings))
+            .route("/settings/2fa/totp", post(routes::enroll_totp))
+            .route("/settings/2fa/totp/confirm", post(routes::confirm_totp))
+            .route(
+                "/settings/2fa/recovery-codes",
+                get(routes::get_recovery_codes).post(routes::regenerate_recovery_codes),
+            )
+            .route("/delete-account", post(routes::delete_account))
+            .route("/me", get(routes::get_me).patch(routes::update_me))
+            .route("/sessions", get(routes::get_sessions))
+            .route("/sessions/:id", delete(routes::delete_session))
+            .route("/admin/users/status", post(routes::update_user_status))
+            .route("/.well-known/jwks.json", get(routes::jwks))
             .with_state(app_state)
+            .layer(middleware::from_fn(csrf::verify_origin))
             .layer(cors)
             .layer(
                 TraceLayer::new_for_http()
@@ -64,7 +112,10 @@ impl Application {
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         Ok(Application { server, address })
     }
@@ -95,6 +146,31 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing Token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid Token"),
+            AuthAPIError::InvalidAudience => (StatusCode::BAD_REQUEST, "Invalid audience"),
+            AuthAPIError::InvalidClient => (StatusCode::UNAUTHORIZED, "Invalid client"),
+            AuthAPIError::UntrustedOrigin => (StatusCode::FORBIDDEN, "Untrusted origin"),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
+            AuthAPIError::AccountInactive => {
+                (StatusCode::FORBIDDEN, "Account suspended or disabled")
+            }
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::AccountLocked => (
+                StatusCode::LOCKED,
+                "Account locked after too many failed logins",
+            ),
+            AuthAPIError::TooMany2FAAttempts => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many incorrect 2FA codes, log in again",
+            ),
+            AuthAPIError::Resend2FATooSoon => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "2FA code was sent recently, try again later",
+            ),
+            AuthAPIError::TooMany2FAResends => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "2FA code resent too many times, log in again",
+            ),
         };
 
         let body = Json(ErrorResponse {
@@ -4,6 +4,9 @@ use auth_service::domain::Email;
 use auth_service::services::postgres_user_store::PostgresUserStore;
 use auth_service::services::postmark_email_client::PostmarkEmailClient;
 use auth_service::services::redis_banned_token_store::RedisBannedTokenStore;
+use auth_service::services::redis_login_attempt_store::RedisLoginAttemptStore;
+use auth_service::services::redis_refresh_token_store::RedisRefreshTokenStore;
+use auth_service::services::redis_session_store::RedisSessionStore;
 use auth_service::services::redis_two_fa_code_store::RedisTwoFACodeStore;
 use auth_service::utils::constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME};
 use auth_service::utils::tracing::init_tracing;
@@ -25,13 +28,19 @@ async fn main() {
 
     let user_store = PostgresUserStore::new(pg_pool);
     let banned_token_store = RedisBannedTokenStore::new(redis_conn.clone());
+    let refresh_token_store = RedisRefreshTokenStore::new(redis_conn.clone());
+    let session_store = RedisSessionStore::new(redis_conn.clone());
     let two_fa_code_store = RedisTwoFACodeStore::new(redis_conn.clone());
+    let login_attempt_store = RedisLoginAttemptStore::new(redis_conn.clone());
     let email_client = Arc::new(configure_postmark_email_client());
     let app_state = AppState {
         user_store: Arc::new(RwLock::new(user_store)),
         banned_token_store: Arc::new(RwLock::new(banned_token_store)),
+        refresh_token_store: Arc::new(RwLock::new(refresh_token_store)),
+        session_store: Arc::new(RwLock::new(session_store)),
         two_fa_code_store: Arc::new(RwLock::new(two_fa_code_store)),
-        email_client: email_client,
+        login_attempt_store: Arc::new(RwLock::new(login_attempt_store)),
+        email_client,
     };
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
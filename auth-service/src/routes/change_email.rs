@@ -0,0 +1,273 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse},
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    domain::{data_stores::UserStoreError, AuthAPIError, Email, Password},
+    utils::{
+        auth::{end_all_sessions, remove_auth_cookies},
+        authenticated_user::AuthenticatedUser,
+        constants::AUTH_SERVICE_URL,
+        email_token::{
+            generate_email_change_tokens, redeem_email_token, EmailTokenClaims, EmailTokenPurpose,
+        },
+        lockout::check_password,
+    },
+};
+
+#[tracing::instrument(name = "Change email", skip_all)]
+pub async fn change_email(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let new_email =
+        Email::parse(request.new_email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    if new_email == user.email {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    check_password(&state, &user.email, &password).await?;
+
+    let user_id = {
+        let user_store = state.user_store.read().await;
+
+        if user_store.get_user(&new_email).await.is_ok() {
+            return Err(AuthAPIError::UserAlreadyExists);
+        }
+
+        user_store
+            .get_user(&user.email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+            .id
+    };
+
+    let (confirm_token, undo_token) =
+        generate_email_change_tokens(user_id, &user.email, &new_email)
+            .map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .email_client
+        .send_email(
+            &new_email,
+            "Confirm your new email address",
+            &format!(
+                "Confirm your new email address by following this link: {}/confirm-email-change?token={}",
+                AUTH_SERVICE_URL.as_str(),
+                confirm_token.expose_secret()
+            ),
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .email_client
+        .send_email(
+            &user.email,
+            "Your email address is being changed",
+            &format!(
+                "Someone asked to move your account to {}. If it was not you, undo the change by following this link: {}/undo-email-change?token={}",
+                new_email.as_ref().expose_secret(),
+                AUTH_SERVICE_URL.as_str(),
+                undo_token.expose_secret()
+            ),
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(ChangeEmailResponse {
+        message: "Follow the link sent to the new email address to confirm the change.".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// The links in the emails only open a page, which asks the user to press a
+// button that makes the POST. Link scanners that follow every link in an
+// email change nothing.
+pub async fn email_change_page() -> Html<&'static str> {
+    Html(include_str!("../../assets/email-change.html"))
+}
+
+#[tracing::instrument(name = "Confirm email change", skip_all)]
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<EmailChangeTokenRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match redeem_email_token(
+        &request.token,
+        EmailTokenPurpose::ConfirmEmailChange,
+        state.banned_token_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => claims,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let (user_id, email, new_email) = match parse_email_change(claims) {
+        Ok(change) => change,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    if let Err(e) = move_account(&state, user_id, &email, &new_email).await {
+        return (jar, Err(e));
+    }
+
+    let response = Json(ChangeEmailResponse {
+        message: "Email changed successfully! Please log in again.".to_owned(),
+    });
+
+    (remove_auth_cookies(jar), Ok((StatusCode::OK, response)))
+}
+
+#[tracing::instrument(name = "Undo email change", skip_all)]
+pub async fn undo_email_change(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<EmailChangeTokenRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let claims = match redeem_email_token(
+        &request.token,
+        EmailTokenPurpose::UndoEmailChange,
+        state.banned_token_store.clone(),
+    )
+    .await
+    {
+        Ok(claims) => claims,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    // Stop the change from being confirmed later
+    if let Some(cancels) = &claims.cancels {
+        if let Err(e) = state
+            .banned_token_store
+            .write()
+            .await
+            .add_token(cancels.to_owned(), claims.exp)
+            .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+    }
+
+    let (user_id, email, new_email) = match parse_email_change(claims) {
+        Ok(change) => change,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    // The change was only confirmed if this user's account is the one at the
+    // new address. Any other account there is left alone.
+    let confirmed = state
+        .user_store
+        .read()
+        .await
+        .get_user(&new_email)
+        .await
+        .is_ok_and(|user| user.id == user_id);
+
+    if !confirmed {
+        let response = Json(ChangeEmailResponse {
+            message: "Email change cancelled.".to_owned(),
+        });
+
+        return (jar, Ok((StatusCode::OK, response)));
+    }
+
+    if let Err(e) = move_account(&state, user_id, &new_email, &email).await {
+        return (jar, Err(e));
+    }
+
+    let response = Json(ChangeEmailResponse {
+        message: "Email change undone. If you did not ask for it, reset your password.".to_owned(),
+    });
+
+    (remove_auth_cookies(jar), Ok((StatusCode::OK, response)))
+}
+
+// Returns the id of the account being moved, its old address and its new one
+fn parse_email_change(claims: EmailTokenClaims) -> Result<(Uuid, Email, Email), AuthAPIError> {
+    let user_id = claims.user_id.ok_or(AuthAPIError::InvalidToken)?;
+    let email = Email::parse(Secret::new(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)?;
+    let new_email = claims
+        .new_email
+        .and_then(|new_email| Email::parse(Secret::new(new_email)).ok())
+        .ok_or(AuthAPIError::InvalidToken)?;
+
+    Ok((user_id, email, new_email))
+}
+
+// Moves the account to another address, which the user has just proven they
+// can read, and logs it out everywhere so no token for the old address survives.
+// Nothing moves unless the account at `from` is still the one the link was for.
+async fn move_account(
+    state: &AppState,
+    user_id: Uuid,
+    from: &Email,
+    to: &Email,
+) -> Result<(), AuthAPIError> {
+    {
+        let mut user_store = state.user_store.write().await;
+
+        match user_store.get_user(from).await {
+            Ok(user) if user.id == user_id => (),
+            Ok(_) | Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+
+        match user_store.update_email(from, to.clone()).await {
+            Ok(()) => (),
+            Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+            Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+
+        user_store
+            .mark_email_verified(to)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_codes(from)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    end_all_sessions(state, from)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
+#[derive(Deserialize)]
+pub struct ChangeEmailRequest {
+    #[serde(rename = "newEmail")]
+    pub new_email: Secret<String>,
+    pub password: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct EmailChangeTokenRequest {
+    pub token: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
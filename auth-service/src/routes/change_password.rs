@@ -0,0 +1,68 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Password},
+    utils::{auth::end_session, authenticated_user::AuthenticatedUser, lockout::check_password},
+};
+
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let current_password =
+        Password::parse(request.current_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let new_password =
+        Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    check_password(&state, &user.email, &current_password).await?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .update_password(&user.email, new_password)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Only the session that changed the password stays logged in
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    for session in sessions
+        .into_iter()
+        .filter(|session| session.id != user.claims.sid)
+    {
+        end_session(&state, &session.id)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+    }
+
+    let response = Json(ChangePasswordResponse {
+        message: "Password changed successfully!".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
@@ -0,0 +1,86 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Password},
+    utils::{
+        auth::{end_all_sessions, remove_auth_cookies},
+        authenticated_user::AuthenticatedUser,
+        lockout::check_password,
+    },
+};
+
+#[tracing::instrument(name = "Delete account", skip_all)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    user: AuthenticatedUser,
+    Json(request): Json<DeleteAccountRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let password = match Password::parse(request.password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    if let Err(e) = check_password(&state, &user.email, &password).await {
+        return (jar, Err(e));
+    }
+
+    if let Err(e) = state
+        .user_store
+        .write()
+        .await
+        .delete_user(&user.email)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    // A login that was waiting on its 2FA code must not be able to finish
+    if let Err(e) = state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_codes(&user.email)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    if let Err(e) = end_all_sessions(&state, &user.email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    if let Err(e) = state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(user.claims.jti, user.claims.exp as u64)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    // The account is already gone, so a failed confirmation must not fail the request
+    if let Err(e) = state
+        .email_client
+        .send_email(
+            &user.email,
+            "Your account has been deleted",
+            "Your account and everything associated with it has been deleted.",
+        )
+        .await
+    {
+        tracing::error!("failed to send account deletion confirmation: {:?}", e);
+    }
+
+    (remove_auth_cookies(jar), Ok(StatusCode::OK))
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: Secret<String>,
+}
@@ -0,0 +1,59 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email},
+    utils::email_token::{generate_email_token, EmailTokenPurpose},
+};
+
+#[tracing::instrument(name = "Forgot password", skip_all)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user_exists = state.user_store.read().await.get_user(&email).await.is_ok();
+
+    // Whatever happens next, the response must not reveal whether the account exists
+    if user_exists {
+        if let Err(e) = send_reset_token(&state, &email).await {
+            tracing::error!("failed to send password reset token: {:?}", e);
+        }
+    }
+
+    let response = Json(ForgotPasswordResponse {
+        message: "If an account exists for this email, a reset token has been sent to it."
+            .to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+async fn send_reset_token(state: &AppState, email: &Email) -> color_eyre::eyre::Result<()> {
+    let token = generate_email_token(email, EmailTokenPurpose::ResetPassword)?;
+
+    state
+        .email_client
+        .send_email(
+            email,
+            "Reset your password",
+            &format!(
+                "Your password reset token is: {}\nIt expires in one hour and can only be used once.",
+                token.expose_secret()
+            ),
+        )
+        .await
+}
+
+#[derive(Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ForgotPasswordResponse {
+    pub message: String,
+}
@@ -0,0 +1,71 @@
+use axum::{extract::State, http::HeaderMap, response::IntoResponse, Form, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{
+        auth::inspect_token, client_credentials::authenticate_client,
+        constants::INTROSPECTION_CLIENTS,
+    },
+};
+
+#[tracing::instrument(name = "Introspect", skip_all)]
+pub async fn introspect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(request): Form<IntrospectionRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    if authenticate_client(&headers, &INTROSPECTION_CLIENTS).is_none() {
+        return Err(AuthAPIError::InvalidClient);
+    }
+
+    // RFC 7662 says nothing about why a token is inactive, so every failure looks the same
+    let response = match inspect_token(
+        &request.token,
+        state.banned_token_store,
+        state.session_store,
+        state.user_store,
+    )
+    .await
+    {
+        Ok(claims) => IntrospectionResponse {
+            active: true,
+            sub: Some(claims.sub),
+            username: Some(claims.email),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            // Tokens carry no scopes, so the space-separated list is empty
+            scope: Some(String::new()),
+            // Tokens are issued for an audience rather than an OAuth client
+            client_id: Some(claims.aud),
+        },
+        Err(_) => IntrospectionResponse::default(),
+    };
+
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: Secret<String>,
+    pub token_type_hint: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
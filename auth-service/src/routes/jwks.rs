@@ -0,0 +1,8 @@
+use axum::{response::IntoResponse, Json};
+
+use crate::utils::keys::public_jwks;
+
+#[tracing::instrument(name = "JWKS", skip_all)]
+pub async fn jwks() -> impl IntoResponse {
+    Json(public_jwks())
+}
@@ -4,21 +4,29 @@ use color_eyre::eyre::eyre;
 use rand::Rng;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
     app_state::AppState,
     domain::{
-        data_stores::{LoginAttemptId, TwoFACode},
+        data_stores::{LoginAttemptId, Session, TwoFACode},
         error::AuthAPIError,
+        user::{TwoFAMethod, UserStatus},
         Email, Password,
     },
-    utils::auth::generate_auth_cookie,
+    utils::{
+        auth::{login_timestamp, parse_audience, start_session},
+        client_info::ClientInfo,
+        constants::REQUIRE_VERIFIED_EMAIL,
+        lockout::check_password,
+    },
 };
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
     jar: CookieJar,
+    client: ClientInfo,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let email = match Email::parse(Secret::new(request.email)) {
@@ -31,26 +39,64 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let user_store = state.user_store.write().await;
+    let audience = match parse_audience(request.audience) {
+        Ok(audience) => audience,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidAudience)),
+    };
 
-    if user_store.validate_user(&email, &password).await.is_err() {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    if let Err(e) = check_password(&state, &email, &password).await {
+        return (jar, Err(e));
     }
 
-    let user = match user_store.get_user(&email).await {
+    let user = match state.user_store.read().await.get_user(&email).await {
         Ok(user) => user,
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
+    if user.status != UserStatus::Active {
+        return (jar, Err(AuthAPIError::AccountInactive));
+    }
+
+    if *REQUIRE_VERIFIED_EMAIL && !user.email_verified {
+        return (jar, Err(AuthAPIError::EmailNotVerified));
+    }
+
     match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+        true => handle_2fa(&user.email, user.two_fa_method, audience, &state, jar).await,
+        false => {
+            let logged_in_at = match login_timestamp(&state, &user.email).await {
+                Ok(logged_in_at) => logged_in_at,
+                Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+            };
+
+            let session = Session::new(
+                user.email,
+                request.device,
+                client.ip,
+                client.user_agent,
+                logged_in_at,
+            );
+
+            if let Err(e) = state
+                .user_store
+                .write()
+                .await
+                .record_login(&session.email, session.created_at)
+                .await
+            {
+                return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+            }
+
+            handle_no_2fa(session, user.id, audience, &state, jar).await
+        }
     }
 }
 
 #[tracing::instrument(name = "Handle2FA", skip_all)]
 async fn handle_2fa(
     email: &Email,
+    two_fa_method: TwoFAMethod,
+    audience: String,
     state: &AppState,
     jar: CookieJar,
 ) -> (
@@ -70,62 +116,75 @@ async fn handle_2fa(
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(eyre!(e)))),
     };
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
-
-    match two_fa_code_store
+    if let Err(e) = state
+        .two_fa_code_store
+        .write()
+        .await
         .add_code(
             email.to_owned(),
             login_attempt_id.clone(),
             two_fa_code.clone(),
+            audience,
         )
         .await
     {
-        Ok(_) => {
-            match state
-                .email_client
-                .send_email(
-                    &email,
-                    "Two Factor Authentication Code",
-                    &format!("Your code is: {}", two_fa_code.as_ref().expose_secret()),
-                )
-                .await
-            {
-                Ok(_) => {
-                    return (
-                        jar,
-                        Ok((
-                            StatusCode::PARTIAL_CONTENT,
-                            axum::Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
-                                message: "2FA required".to_owned(),
-                                login_attempt_id: login_attempt_id
-                                    .as_ref()
-                                    .expose_secret()
-                                    .to_owned(),
-                            })),
-                        )),
-                    );
-                }
-                Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
-            }
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    // Authenticator apps generate their own codes, so nothing is sent and
+    // verify-2fa only uses the stored login attempt id for them.
+    if two_fa_method == TwoFAMethod::Email {
+        if let Err(e) = state
+            .email_client
+            .send_email(
+                email,
+                "Two Factor Authentication Code",
+                &format!("Your code is: {}", two_fa_code.as_ref().expose_secret()),
+            )
+            .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)));
         }
-        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
     }
+
+    (
+        jar,
+        Ok((
+            StatusCode::PARTIAL_CONTENT,
+            axum::Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
+                message: "2FA required".to_owned(),
+                login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
+                two_fa_method: two_fa_method.as_ref().to_owned(),
+            })),
+        )),
+    )
 }
 
 #[tracing::instrument(name = "HandleNo2FA", skip_all)]
 async fn handle_no_2fa(
-    email: &Email,
+    session: Session,
+    user_id: Uuid,
+    audience: String,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
+    let (auth_cookie, refresh_cookie) = match start_session(
+        session,
+        user_id,
+        audience,
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
+        Ok(cookies) => cookies,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
     (
         updated_jar,
@@ -137,6 +196,10 @@ async fn handle_no_2fa(
 pub struct LoginRequest {
     pub email: String,
     pub password: Secret<String>,
+    #[serde(default)]
+    pub audience: Option<String>,
+    #[serde(default)]
+    pub device: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -151,4 +214,6 @@ pub struct TwoFactorAuthResponse {
     pub message: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: String,
 }
@@ -0,0 +1,38 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{
+        auth::{end_all_sessions, remove_auth_cookies},
+        authenticated_user::AuthenticatedUser,
+    },
+};
+
+#[tracing::instrument(name = "Logout all", skip_all)]
+pub async fn logout_all(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    user: AuthenticatedUser,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    // Every token and refresh token family issued before now stops working
+    if let Err(e) = end_all_sessions(&state, &user.email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    // The current token may have been issued within the same second, so ban it explicitly
+    if let Err(e) = state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(user.claims.jti, user.claims.exp as u64)
+        .await
+    {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    let updated_jar = remove_auth_cookies(jar);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
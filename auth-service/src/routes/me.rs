@@ -0,0 +1,103 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{user::User, AuthAPIError, DisplayName},
+    utils::authenticated_user::AuthenticatedUser,
+};
+
+#[tracing::instrument(name = "Get me", skip_all)]
+pub async fn get_me(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let stored_user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((StatusCode::OK, Json(MeResponse::from(stored_user))))
+}
+
+#[tracing::instrument(name = "Update me", skip_all)]
+pub async fn update_me(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<UpdateMeRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let mut user_store = state.user_store.write().await;
+
+    let mut stored_user = user_store
+        .get_user(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // A field left out of the request is left as it is, while null clears it
+    if let Some(display_name) = request.display_name {
+        stored_user.display_name = display_name
+            .map(DisplayName::parse)
+            .transpose()
+            .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        user_store
+            .update_user(stored_user.clone())
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok((StatusCode::OK, Json(MeResponse::from(stored_user))))
+}
+
+#[derive(Deserialize)]
+pub struct UpdateMeRequest {
+    #[serde(
+        default,
+        rename = "displayName",
+        deserialize_with = "deserialize_present"
+    )]
+    pub display_name: Option<Option<String>>,
+}
+
+// Tells a field set to null apart from one that is missing
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeResponse {
+    pub id: String,
+    pub email: String,
+    pub display_name: Option<String>,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: String,
+    pub email_verified: bool,
+    pub created_at: u64,
+    pub last_login_at: Option<u64>,
+}
+
+impl From<User> for MeResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id.to_string(),
+            email: user.email.as_ref().expose_secret().to_owned(),
+            display_name: user.display_name.map(|name| name.as_ref().to_owned()),
+            requires_2fa: user.requires_2fa,
+            two_fa_method: user.two_fa_method.as_ref().to_owned(),
+            email_verified: user.email_verified,
+            created_at: user.created_at,
+            last_login_at: user.last_login_at,
+        }
+    }
+}
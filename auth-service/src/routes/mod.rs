@@ -1,11 +1,49 @@
+mod change_email;
+mod change_password;
+mod delete_account;
+mod forgot_password;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod me;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
+mod resend_verification;
+mod reset_password;
+mod sessions;
 mod signup;
+mod totp;
+mod two_fa_settings;
+mod unlock_account;
+mod user_status;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
 
+pub use change_email::*;
+pub use change_password::*;
+pub use delete_account::*;
+pub use forgot_password::*;
+pub use introspect::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use logout_all::*;
+pub use me::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use resend_2fa::*;
+pub use resend_verification::*;
+pub use reset_password::*;
+pub use sessions::*;
 pub use signup::*;
+pub use totp::*;
+pub use two_fa_settings::*;
+pub use unlock_account::*;
+pub use user_status::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
@@ -0,0 +1,88 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Password},
+    utils::{
+        auth::issue_recovery_codes, authenticated_user::AuthenticatedUser, lockout::check_password,
+    },
+};
+
+#[tracing::instrument(name = "Get recovery codes", skip_all)]
+pub async fn get_recovery_codes(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user_store = state.user_store.read().await;
+
+    let stored_user = user_store
+        .get_user(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let remaining = user_store
+        .count_recovery_codes(stored_user.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(RecoveryCodesCountResponse { remaining }),
+    ))
+}
+
+// New codes replace all the old ones. A stolen session alone is not enough to
+// get them, so the password has to be entered again.
+#[tracing::instrument(name = "Regenerate recovery codes", skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<RegenerateRecoveryCodesRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    check_password(&state, &user.email, &password).await?;
+
+    let mut user_store = state.user_store.write().await;
+
+    let stored_user = user_store
+        .get_user(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if !stored_user.requires_2fa {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let codes = issue_recovery_codes(&mut *user_store, stored_user.id)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(RecoveryCodesResponse {
+        recovery_codes: codes
+            .iter()
+            .map(|code| code.expose_secret().to_owned())
+            .collect(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct RecoveryCodesCountResponse {
+    pub remaining: usize,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
@@ -0,0 +1,165 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        data_stores::{
+            RefreshToken, RefreshTokenRecord, RefreshTokenStoreError, SessionStoreError,
+            UserStoreError,
+        },
+        user::UserStatus,
+        AuthAPIError,
+    },
+    utils::{
+        auth::{end_session, generate_auth_cookie, generate_refresh_cookie, now_timestamp},
+        constants::{COOKIE_CONFIG, REFRESH_TOKEN_COOKIE_NAME},
+    },
+};
+
+#[tracing::instrument(name = "Refresh", skip_all)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let cookie = match jar.get(&COOKIE_CONFIG.name(REFRESH_TOKEN_COOKIE_NAME)) {
+        Some(cookie) => cookie,
+        None => return (jar, Err(AuthAPIError::MissingToken)),
+    };
+
+    let token = match RefreshToken::parse(Secret::new(cookie.value().to_owned())) {
+        Ok(token) => token,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    // Tokens from logins that happened before "log out everywhere" are dead.
+    // The epoch is read up front so that the banned token store is never
+    // locked while the refresh token store is.
+    let email = match state
+        .refresh_token_store
+        .read()
+        .await
+        .get_token(&token)
+        .await
+    {
+        Ok(record) => record.email,
+        Err(RefreshTokenStoreError::TokenNotFound) => {
+            return (jar, Err(AuthAPIError::InvalidToken))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let revoked_at = match state
+        .banned_token_store
+        .read()
+        .await
+        .get_revocation_epoch(email.as_ref().expose_secret())
+        .await
+    {
+        Ok(revoked_at) => revoked_at,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // Hold the write lock while checking and consuming the token, so two
+    // concurrent requests cannot both redeem it.
+    let record = {
+        let mut refresh_token_store = state.refresh_token_store.write().await;
+
+        let record = match refresh_token_store.get_token(&token).await {
+            Ok(record) => record,
+            Err(RefreshTokenStoreError::TokenNotFound) => {
+                return (jar, Err(AuthAPIError::InvalidToken))
+            }
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        };
+
+        if record.used {
+            // A rotated token should never come back. If it does, it has been
+            // stolen, so nobody holding a token from this login can be trusted.
+            // Ending the session kills its access tokens along with the family.
+            tracing::warn!("refresh token reuse detected, ending session");
+
+            // `end_session` takes the refresh token store lock itself
+            drop(refresh_token_store);
+
+            if let Err(e) = end_session(&state, &record.family_id).await {
+                return (jar, Err(AuthAPIError::UnexpectedError(e)));
+            }
+
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+
+        if revoked_at.is_some_and(|revoked_at| record.authenticated_at <= revoked_at) {
+            if let Err(e) = refresh_token_store.revoke_family(&record.family_id).await {
+                return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+            }
+
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+
+        // The refresh token family belongs to a session, and dies with it
+        let mut session_store = state.session_store.write().await;
+
+        match session_store.get_session(&record.family_id).await {
+            Ok(_) => (),
+            Err(SessionStoreError::SessionNotFound) => {
+                if let Err(e) = refresh_token_store.revoke_family(&record.family_id).await {
+                    return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+                }
+
+                return (jar, Err(AuthAPIError::InvalidToken));
+            }
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
+
+        if let Err(e) = session_store
+            .touch_session(&record.family_id, now_timestamp())
+            .await
+        {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+
+        if let Err(e) = refresh_token_store.mark_token_used(&token).await {
+            return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        }
+
+        record
+    };
+
+    // The token carries the user's id, which the refresh token does not know
+    let user = match state.user_store.read().await.get_user(&record.email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if user.status != UserStatus::Active {
+        return (jar, Err(AuthAPIError::AccountInactive));
+    }
+
+    let auth_cookie =
+        match generate_auth_cookie(user.id, &record.email, &record.audience, &record.family_id) {
+            Ok(cookie) => cookie,
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+        };
+
+    let refresh_cookie = match generate_refresh_cookie(
+        RefreshTokenRecord::new(
+            record.email,
+            record.audience,
+            record.family_id,
+            record.authenticated_at,
+        ),
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
+        Ok(cookie) => cookie,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
@@ -0,0 +1,91 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACodeStoreError, UserStoreError},
+        user::TwoFAMethod,
+        AuthAPIError, Email,
+    },
+    utils::constants::{MAX_2FA_RESENDS, RESEND_2FA_COOLDOWN_SECONDS},
+};
+
+// Sends the pending code again, so a delayed or lost email does not mean
+// entering the password again. The code itself is unchanged, which keeps the
+// count of wrong guesses against it.
+#[tracing::instrument(name = "Resend 2FA code", skip_all)]
+pub async fn resend_2fa(
+    State(state): State<AppState>,
+    Json(request): Json<Resend2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let login_attempt_id = LoginAttemptId::parse(request.login_attempt_id)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // Authenticator app codes are never emailed
+    if user.two_fa_method != TwoFAMethod::Email {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+
+    match two_fa_code_store.get_code(&login_attempt_id).await {
+        Ok((challenge_email, _)) if challenge_email == email => (),
+        Ok(_) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            return Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let code = two_fa_code_store
+        .record_resend(
+            &login_attempt_id,
+            *RESEND_2FA_COOLDOWN_SECONDS,
+            *MAX_2FA_RESENDS,
+        )
+        .await
+        .map_err(|e| match e {
+            TwoFACodeStoreError::LoginAttemptIdNotFound => AuthAPIError::IncorrectCredentials,
+            TwoFACodeStoreError::ResendTooSoon => AuthAPIError::Resend2FATooSoon,
+            TwoFACodeStoreError::TooManyResends => AuthAPIError::TooMany2FAResends,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    drop(two_fa_code_store);
+
+    state
+        .email_client
+        .send_email(
+            &email,
+            "Two Factor Authentication Code",
+            &format!("Your code is: {}", code.as_ref().expose_secret()),
+        )
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(Resend2FAResponse {
+        message: "2FA code sent".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct Resend2FARequest {
+    pub email: Secret<String>,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct Resend2FAResponse {
+    pub message: String,
+}
@@ -0,0 +1,49 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email},
+    utils::email_token::send_verification_email,
+};
+
+#[tracing::instrument(name = "Resend verification email", skip_all)]
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    Json(request): Json<ResendVerificationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let unverified = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .is_ok_and(|user| !user.email_verified);
+
+    // Whatever happens next, the response must not reveal whether the account exists
+    if unverified {
+        if let Err(e) = send_verification_email(&*state.email_client, &email).await {
+            tracing::error!("failed to send verification email: {:?}", e);
+        }
+    }
+
+    let response = Json(ResendVerificationResponse {
+        message: "If an unverified account exists for this email, a new link has been sent to it."
+            .to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ResendVerificationResponse {
+    pub message: String,
+}
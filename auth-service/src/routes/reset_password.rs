@@ -0,0 +1,67 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{data_stores::UserStoreError, AuthAPIError, Email, Password},
+    utils::{
+        auth::end_all_sessions,
+        email_token::{redeem_email_token, EmailTokenPurpose},
+    },
+};
+
+#[tracing::instrument(name = "Reset password", skip_all)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    // Check the new password first, so a rejected one does not use up the token
+    let password =
+        Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let claims = redeem_email_token(
+        &request.token,
+        EmailTokenPurpose::ResetPassword,
+        state.banned_token_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(Secret::new(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    match state
+        .user_store
+        .write()
+        .await
+        .update_password(&email, password)
+        .await
+    {
+        Ok(()) => (),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // Whoever knew the old password may still be logged in
+    end_all_sessions(&state, &email)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(ResetPasswordResponse {
+        message: "Password reset successfully!".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ResetPasswordResponse {
+    pub message: String,
+}
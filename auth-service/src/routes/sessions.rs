@@ -0,0 +1,85 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{data_stores::SessionStoreError, AuthAPIError},
+    utils::{
+        auth::{end_session, remove_auth_cookies},
+        authenticated_user::AuthenticatedUser,
+    },
+};
+
+#[tracing::instrument(name = "Get sessions", skip_all)]
+pub async fn get_sessions(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = sessions
+        .into_iter()
+        .map(|session| SessionResponse {
+            current: session.id == user.claims.sid,
+            id: session.id,
+            device: session.device,
+            ip: session.ip,
+            user_agent: session.user_agent,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+        })
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[tracing::instrument(name = "Delete session", skip_all)]
+pub async fn delete_session(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    // Someone else's session looks the same as one that does not exist
+    match state.session_store.read().await.get_session(&id).await {
+        Ok(session) if session.email == user.email => (),
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => {
+            return (jar, Err(AuthAPIError::SessionNotFound))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    if let Err(e) = end_session(&state, &id).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    }
+
+    if id == user.claims.sid {
+        return (remove_auth_cookies(jar), Ok(StatusCode::NO_CONTENT));
+    }
+
+    (jar, Ok(StatusCode::NO_CONTENT))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    pub id: String,
+    pub device: Option<String>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: u64,
+    pub last_seen_at: u64,
+    pub current: bool,
+}
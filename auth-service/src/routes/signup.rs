@@ -1,10 +1,11 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{email::Email, error::AuthAPIError, password::Password, user::User},
+    domain::{email::Email, error::AuthAPIError, password::Password, user::User, RecoveryCode},
+    utils::email_token::send_verification_email,
 };
 
 #[tracing::instrument(name = "Signup", skip_all)]
@@ -24,12 +25,38 @@ pub async fn signup(
         return Err(AuthAPIError::UserAlreadyExists);
     }
 
-    if let Err(e) = user_store.add_user(user).await {
+    let email = user.email.clone();
+
+    // Generated up front and stored with the user, so a failure cannot leave
+    // a 2FA account behind whose recovery codes were never shown
+    let recovery_codes = user.requires_2fa.then(RecoveryCode::generate_set);
+    let code_hashes = recovery_codes
+        .iter()
+        .flatten()
+        .map(RecoveryCode::hash)
+        .collect();
+
+    if let Err(e) = user_store.add_user(user, code_hashes).await {
         return Err(AuthAPIError::UnexpectedError(e.into()));
     }
 
+    drop(user_store);
+
+    // The account already exists, so failing here would lose the recovery
+    // codes and leave the address unable to sign up again. The link can be
+    // sent again from /resend-verification instead.
+    if let Err(e) = send_verification_email(&*state.email_client, &email).await {
+        tracing::error!("failed to send verification email: {:?}", e);
+    }
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
+        recovery_codes: recovery_codes.map(|codes| {
+            codes
+                .iter()
+                .map(|code| code.formatted().expose_secret().to_owned())
+                .collect()
+        }),
     });
 
     Ok((StatusCode::CREATED, response))
@@ -46,4 +73,10 @@ pub struct SignupRequest {
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 pub struct SignupResponse {
     pub message: String,
+    #[serde(
+        default,
+        rename = "recoveryCodes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recovery_codes: Option<Vec<String>>,
 }
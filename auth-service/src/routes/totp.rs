@@ -0,0 +1,176 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{user::TwoFAMethod, AuthAPIError, Password, TotpCode, TotpSecret},
+    utils::{
+        auth::{issue_recovery_codes, now_timestamp},
+        authenticated_user::AuthenticatedUser,
+        constants::JWT_ISSUER,
+        lockout::{check_password, check_totp_code},
+    },
+};
+
+// Enrolling only stores a pending secret. Email codes keep working until the
+// user proves their authenticator app produces the right codes.
+//
+// A stolen session must not be enough to swap in an app the attacker holds, so
+// the password is needed, and so is a code from the current app if one is
+// already in use. Only the app holding the pending secret can confirm it.
+#[tracing::instrument(name = "Enroll TOTP", skip_all)]
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<EnrollTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    check_password(&state, &user.email, &password).await?;
+
+    let stored_user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let uses_totp = stored_user.requires_2fa && stored_user.two_fa_method == TwoFAMethod::Totp;
+
+    let used_totp_step = match (uses_totp, request.two_fa_code) {
+        (false, _) => None,
+        (true, Some(code)) => Some(check_totp_code(&state, &stored_user, code).await?),
+        (true, None) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let mut user_store = state.user_store.write().await;
+
+    // Read again, since the user may have changed while the lock was not held
+    let mut stored_user = user_store
+        .get_user(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let secret = TotpSecret::generate();
+    let otpauth_uri = secret.provisioning_uri(&JWT_ISSUER, &stored_user.email);
+
+    stored_user.pending_totp_secret = Some(secret.clone());
+    stored_user.totp_last_used_step = used_totp_step.or(stored_user.totp_last_used_step);
+
+    user_store
+        .update_user(stored_user)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(EnrollTotpResponse {
+        secret: secret.as_ref().expose_secret().to_owned(),
+        otpauth_uri: otpauth_uri.expose_secret().to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Confirm TOTP", skip_all)]
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let code =
+        TotpCode::parse(request.two_fa_code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let password =
+        Password::parse(request.password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    check_password(&state, &user.email, &password).await?;
+
+    let mut user_store = state.user_store.write().await;
+
+    let mut stored_user = user_store
+        .get_user(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let Some(secret) = stored_user.pending_totp_secret.take() else {
+        return Err(AuthAPIError::InvalidCredentials);
+    };
+
+    let Some(step) = secret.verify(&code, now_timestamp(), None) else {
+        return Err(AuthAPIError::IncorrectCredentials);
+    };
+
+    let enables_2fa = !stored_user.requires_2fa;
+
+    stored_user.totp_secret = Some(secret);
+    stored_user.totp_last_used_step = Some(step);
+    stored_user.two_fa_method = TwoFAMethod::Totp;
+    stored_user.requires_2fa = true;
+
+    user_store
+        .update_user(stored_user.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Switching from email codes keeps the recovery codes the user already has
+    let recovery_codes = if enables_2fa {
+        Some(
+            issue_recovery_codes(&mut *user_store, stored_user.id)
+                .await
+                .map_err(AuthAPIError::UnexpectedError)?,
+        )
+    } else {
+        None
+    };
+
+    let response = Json(ConfirmTotpResponse {
+        requires_2fa: stored_user.requires_2fa,
+        two_fa_method: stored_user.two_fa_method.as_ref().to_owned(),
+        recovery_codes: recovery_codes.map(|codes| {
+            codes
+                .iter()
+                .map(|code| code.expose_secret().to_owned())
+                .collect()
+        }),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrollTotpResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct EnrollTotpRequest {
+    pub password: Secret<String>,
+    // A code from the current second factor, needed when 2FA is already on
+    #[serde(default, rename = "2FACode")]
+    pub two_fa_code: Option<Secret<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmTotpRequest {
+    #[serde(rename = "2FACode")]
+    pub two_fa_code: Secret<String>,
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConfirmTotpResponse {
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: String,
+    // Only present when confirming the app is what turned 2FA on
+    #[serde(
+        default,
+        rename = "recoveryCodes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recovery_codes: Option<Vec<String>>,
+}
@@ -0,0 +1,138 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        user::{TwoFAMethod, User},
+        AuthAPIError, Password,
+    },
+    utils::{
+        auth::issue_recovery_codes,
+        authenticated_user::AuthenticatedUser,
+        lockout::{check_password, check_totp_code},
+    },
+};
+
+#[tracing::instrument(name = "Update 2FA settings", skip_all)]
+pub async fn update_2fa_settings(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(request): Json<TwoFASettingsRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let enabled = request.enabled;
+
+    let stored_user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Turning 2FA on only makes the account safer. Turning it off must be
+    // confirmed by more than a stolen session. That is checked before taking
+    // the write lock, so a slow password hash does not hold up everyone else.
+    let used_totp_step = if stored_user.requires_2fa && !enabled {
+        confirm_disable(&state, &stored_user, request).await?
+    } else {
+        None
+    };
+
+    let mut user_store = state.user_store.write().await;
+
+    // Read again, since the user may have changed while the lock was not held
+    let mut stored_user = user_store
+        .get_user(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let mut recovery_codes = None;
+
+    if stored_user.requires_2fa != enabled {
+        if !enabled {
+            stored_user.two_fa_method = TwoFAMethod::Email;
+            stored_user.totp_secret = None;
+            stored_user.pending_totp_secret = None;
+            stored_user.totp_last_used_step = used_totp_step.or(stored_user.totp_last_used_step);
+        }
+
+        stored_user.requires_2fa = !stored_user.requires_2fa;
+
+        user_store
+            .update_user(stored_user.clone())
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        // Codes from an earlier time 2FA was on must not come back to life
+        if stored_user.requires_2fa {
+            recovery_codes = Some(
+                issue_recovery_codes(&mut *user_store, stored_user.id)
+                    .await
+                    .map_err(AuthAPIError::UnexpectedError)?,
+            );
+        } else {
+            user_store
+                .replace_recovery_codes(stored_user.id, Vec::new())
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        }
+    }
+
+    let response = Json(TwoFASettingsResponse {
+        requires_2fa: stored_user.requires_2fa,
+        recovery_codes: recovery_codes.map(|codes| {
+            codes
+                .iter()
+                .map(|code| code.expose_secret().to_owned())
+                .collect()
+        }),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+// Either the password or an authenticator app code confirms it. Users on
+// email codes need the password. Returns the authenticator app step that
+// confirmed it, if one did, so it cannot be used again.
+async fn confirm_disable(
+    state: &AppState,
+    user: &User,
+    request: TwoFASettingsRequest,
+) -> Result<Option<u64>, AuthAPIError> {
+    if let Some(password) = request.password {
+        let password = Password::parse(password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        return check_password(state, &user.email, &password)
+            .await
+            .map(|()| None);
+    }
+
+    match request.two_fa_code {
+        Some(code) => check_totp_code(state, user, code).await.map(Some),
+        None => Err(AuthAPIError::InvalidCredentials),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TwoFASettingsRequest {
+    pub enabled: bool,
+    #[serde(default)]
+    pub password: Option<Secret<String>>,
+    #[serde(default, rename = "2FACode")]
+    pub two_fa_code: Option<Secret<String>>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct TwoFASettingsResponse {
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    // Only present when 2FA was just turned on
+    #[serde(
+        default,
+        rename = "recoveryCodes",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recovery_codes: Option<Vec<String>>,
+}
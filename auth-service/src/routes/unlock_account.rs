@@ -0,0 +1,61 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse},
+    Json,
+};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email},
+    utils::email_token::{redeem_email_token, EmailTokenPurpose},
+};
+
+// The link in the email only opens a page, whose button makes the POST. A
+// link scanner following it must not unlock the account for whoever is
+// guessing the password.
+pub async fn unlock_account_page() -> Html<&'static str> {
+    Html(include_str!("../../assets/unlock-account.html"))
+}
+
+#[tracing::instrument(name = "Unlock account", skip_all)]
+pub async fn unlock_account(
+    State(state): State<AppState>,
+    Json(request): Json<UnlockAccountRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = redeem_email_token(
+        &request.token,
+        EmailTokenPurpose::UnlockAccount,
+        state.banned_token_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(Secret::new(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    state
+        .login_attempt_store
+        .write()
+        .await
+        .remove_attempts(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = Json(UnlockAccountResponse {
+        message: "Account unlocked. You can log in again.".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct UnlockAccountRequest {
+    pub token: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct UnlockAccountResponse {
+    pub message: String,
+}
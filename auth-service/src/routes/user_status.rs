@@ -0,0 +1,99 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{data_stores::UserStoreError, user::UserStatus, AuthAPIError, Email},
+    utils::{
+        auth::{end_all_sessions, now_timestamp},
+        client_credentials::AdminClient,
+    },
+};
+
+#[tracing::instrument(name = "Update user status", skip_all)]
+pub async fn update_user_status(
+    State(state): State<AppState>,
+    admin: AdminClient,
+    Json(request): Json<UserStatusRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email =
+        Email::parse(Secret::new(request.email)).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let status =
+        UserStatus::parse(&request.status).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let reason = request.reason.trim().to_owned();
+
+    if reason.is_empty() {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let user = {
+        let mut user_store = state.user_store.write().await;
+
+        let mut user = match user_store.get_user(&email).await {
+            Ok(user) => user,
+            Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::UserNotFound),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        };
+
+        user.status = status;
+        user.status_reason = Some(reason);
+        user.status_changed_at = Some(now_timestamp());
+
+        user_store
+            .update_user(user.clone())
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        user
+    };
+
+    tracing::info!(
+        client_id = admin.client_id,
+        status = status.as_ref(),
+        "changed user status"
+    );
+
+    // Refusing new logins is not enough, the user must lose the ones they have
+    if status != UserStatus::Active {
+        state
+            .two_fa_code_store
+            .write()
+            .await
+            .remove_codes(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        end_all_sessions(&state, &email)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+    }
+
+    let response = Json(UserStatusResponse {
+        id: user.id.to_string(),
+        email: user.email.as_ref().expose_secret().to_owned(),
+        status: status.as_ref().to_owned(),
+        reason: user.status_reason,
+        changed_at: user.status_changed_at,
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct UserStatusRequest {
+    pub email: String,
+    pub status: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStatusResponse {
+    pub id: String,
+    pub email: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub changed_at: Option<u64>,
+}
@@ -1,21 +1,31 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
 use secrecy::Secret;
 use serde::Deserialize;
+use uuid::Uuid;
 
 use crate::{
     app_state::AppState,
     domain::{
-        data_stores::{LoginAttemptId, TwoFACode},
-        AuthAPIError, Email,
+        data_stores::{
+            LoginAttemptId, Session, TwoFACode, TwoFACodeStore, TwoFACodeStoreError, UserStoreError,
+        },
+        user::{TwoFAMethod, UserStatus},
+        AuthAPIError, Email, RecoveryCode, TotpCode,
+    },
+    utils::{
+        auth::{login_timestamp, start_session},
+        client_info::ClientInfo,
+        constants::MAX_2FA_ATTEMPTS,
     },
-    utils::auth::generate_auth_cookie,
 };
 
 #[tracing::instrument(name = "Verify 2FA", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
     jar: CookieJar,
+    client: ClientInfo,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let email = match Email::parse(Secret::new(request.email.clone())) {
@@ -29,34 +39,196 @@ pub async fn verify_2fa(
             Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
         };
 
-    let two_fa_code = match TwoFACode::parse(Secret::new(request.two_fa_code.clone())) {
-        Ok(code) => code,
+    let second_factor = match SecondFactor::parse(&request.two_fa_code) {
+        Ok(second_factor) => second_factor,
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
-
-    let code_tuple = match two_fa_code_store.get_code(&email).await {
-        Ok(tuple) => tuple,
+    // The challenge and the user are never locked together, so this cannot
+    // deadlock with /login, which takes them in the other order
+    let (challenge_email, expected_code) = match state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&login_attempt_id)
+        .await
+    {
+        Ok(challenge) => challenge,
         Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
     };
 
-    if code_tuple.0 == login_attempt_id && code_tuple.1 == two_fa_code {
-        let auth_cookie = match generate_auth_cookie(&email) {
-            Ok(cookie) => cookie,
-            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
-        };
+    // Someone else's login attempt is not theirs to finish, or to use up
+    if challenge_email != email {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    // The token is for the audience the login asked for. Repeating it here is
+    // optional, but a different one cannot change it.
+    let audience = match state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_audience(&login_attempt_id)
+        .await
+    {
+        Ok(audience) => audience,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            return (jar, Err(AuthAPIError::IncorrectCredentials))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    if request
+        .audience
+        .as_ref()
+        .is_some_and(|requested| *requested != audience)
+    {
+        return (jar, Err(AuthAPIError::InvalidAudience));
+    }
+
+    let logged_in_at = match login_timestamp(&state, &email).await {
+        Ok(logged_in_at) => logged_in_at,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
 
-        match two_fa_code_store.remove_code(&email).await {
-            Ok(_) => {
-                let updated_jar = jar.add(auth_cookie);
+    let session = Session::new(
+        email.clone(),
+        request.device,
+        client.ip,
+        client.user_agent,
+        logged_in_at,
+    );
 
-                (updated_jar, Ok(StatusCode::OK))
+    let user_id = match verify_second_factor(&state, &session, &second_factor, &expected_code).await
+    {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => {
+            let mut two_fa_code_store = state.two_fa_code_store.write().await;
+            let error = record_miss(&mut *two_fa_code_store, &login_attempt_id).await;
+            return (jar, Err(error));
+        }
+        Err(e) => return (jar, Err(e)),
+    };
+
+    // Whichever request removes the code finishes the login, so two requests
+    // racing with the same code cannot both get a session
+    match state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_code(&login_attempt_id)
+        .await
+    {
+        Ok(()) => (),
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            return (jar, Err(AuthAPIError::IncorrectCredentials))
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    }
+
+    let (auth_cookie, refresh_cookie) = match start_session(
+        session,
+        user_id,
+        audience,
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+    )
+    .await
+    {
+        Ok(cookies) => cookies,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
+
+    (updated_jar, Ok(StatusCode::OK))
+}
+
+// Checks the second factor against the user and records the login. Returns
+// `None` for a wrong code, which the caller counts against the login attempt.
+async fn verify_second_factor(
+    state: &AppState,
+    session: &Session,
+    second_factor: &SecondFactor,
+    expected_code: &TwoFACode,
+) -> Result<Option<Uuid>, AuthAPIError> {
+    let mut user_store = state.user_store.write().await;
+
+    let mut user = user_store
+        .get_user(&session.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if user.status != UserStatus::Active {
+        return Err(AuthAPIError::AccountInactive);
+    }
+
+    match (second_factor, user.two_fa_method, &user.totp_secret) {
+        (SecondFactor::RecoveryCode(code), _, _) => {
+            match user_store.use_recovery_code(user.id, &code.hash()).await {
+                Ok(()) => (),
+                Err(UserStoreError::InvalidCredentials) => return Ok(None),
+                Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
             }
-            Err(e) => (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
         }
-    } else {
-        (jar, Err(AuthAPIError::IncorrectCredentials))
+        (SecondFactor::Code(code), TwoFAMethod::Totp, Some(secret)) => {
+            let Some(step) = secret.verify(code, session.created_at, user.totp_last_used_step)
+            else {
+                return Ok(None);
+            };
+
+            user.totp_last_used_step = Some(step);
+
+            user_store
+                .update_user(user.clone())
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        }
+        (SecondFactor::Code(code), TwoFAMethod::Email, _)
+            if TwoFACode::parse(code.as_ref().clone()).is_ok_and(|code| code == *expected_code) => {
+        }
+        _ => return Ok(None),
+    }
+
+    user_store
+        .record_login(&session.email, session.created_at)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Some(user.id))
+}
+
+// Counts a wrong code against the login attempt. Once the limit is reached the
+// stored code is gone and the user has to log in again.
+async fn record_miss(
+    two_fa_code_store: &mut (dyn TwoFACodeStore + Send + Sync),
+    login_attempt_id: &LoginAttemptId,
+) -> AuthAPIError {
+    match two_fa_code_store
+        .record_failed_attempt(login_attempt_id, *MAX_2FA_ATTEMPTS)
+        .await
+    {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+            AuthAPIError::IncorrectCredentials
+        }
+        Err(TwoFACodeStoreError::TooManyFailedAttempts) => AuthAPIError::TooMany2FAAttempts,
+        Err(e) => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+// A recovery code can be used in place of the code from the user's 2FA method.
+// Any six digits parse as a code, since authenticator app codes can start with
+// zeros. Emailed codes never do, so those are checked when comparing.
+enum SecondFactor {
+    Code(TotpCode),
+    RecoveryCode(RecoveryCode),
+}
+
+impl SecondFactor {
+    fn parse(s: &str) -> Result<Self> {
+        TotpCode::parse(Secret::new(s.to_owned()))
+            .map(Self::Code)
+            .or_else(|_| RecoveryCode::parse(Secret::new(s.to_owned())).map(Self::RecoveryCode))
     }
 }
 
@@ -67,4 +239,8 @@ pub struct Verify2FARequest {
     login_attempt_id: String,
     #[serde(rename = "2FACode")]
     two_fa_code: String,
+    #[serde(default)]
+    audience: Option<String>,
+    #[serde(default)]
+    device: Option<String>,
 }
@@ -0,0 +1,64 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse},
+    Json,
+};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{data_stores::UserStoreError, AuthAPIError, Email},
+    utils::email_token::{redeem_email_token, EmailTokenPurpose},
+};
+
+// The link in the email only opens a page, whose button makes the POST. A
+// link scanner following it must not use up the single-use token.
+pub async fn verify_email_page() -> Html<&'static str> {
+    Html(include_str!("../../assets/verify-email.html"))
+}
+
+#[tracing::instrument(name = "Verify email", skip_all)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = redeem_email_token(
+        &request.token,
+        EmailTokenPurpose::VerifyEmail,
+        state.banned_token_store.clone(),
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = Email::parse(Secret::new(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    match state
+        .user_store
+        .write()
+        .await
+        .mark_email_verified(&email)
+        .await
+    {
+        Ok(()) => (),
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(VerifyEmailResponse {
+        message: "Email verified successfully!".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
@@ -2,14 +2,47 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use secrecy::Secret;
 use serde::Deserialize;
 
-use crate::{app_state::AppState, domain::AuthAPIError, utils::auth::validate_token};
+use crate::{
+    app_state::AppState,
+    domain::AuthAPIError,
+    utils::{
+        auth::{validate_token, validate_token_for_audiences},
+        constants::JWT_AUDIENCES,
+    },
+};
 
 #[tracing::instrument(name = "Verify token", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
     Json(request): Json<VerifyTokenRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    match validate_token(&request.token, state.banned_token_store).await {
+    // Services pass their own audience so they cannot accept tokens meant for someone else
+    let result = match request.audience {
+        Some(audience) => {
+            if !JWT_AUDIENCES.contains(&audience) {
+                return Err(AuthAPIError::InvalidAudience);
+            }
+            validate_token_for_audiences(
+                &request.token,
+                &[audience],
+                state.banned_token_store,
+                state.session_store,
+                state.user_store,
+            )
+            .await
+        }
+        None => {
+            validate_token(
+                &request.token,
+                state.banned_token_store,
+                state.session_store,
+                state.user_store,
+            )
+            .await
+        }
+    };
+
+    match result {
         Ok(_) => (),
         Err(_) => return Err(AuthAPIError::InvalidToken),
     };
@@ -20,4 +53,6 @@ pub async fn verify_token(
 #[derive(Deserialize)]
 pub struct VerifyTokenRequest {
     pub token: Secret<String>,
+    #[serde(default)]
+    pub audience: Option<String>,
 }
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{LoginAttemptStore, LoginAttemptStoreError, LoginAttempts},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashmapLoginAttemptStore {
+    attempts: HashMap<Email, LoginAttempts>,
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for HashmapLoginAttemptStore {
+    async fn get_attempts(&self, email: &Email) -> Result<LoginAttempts, LoginAttemptStoreError> {
+        Ok(self.attempts.get(email).cloned().unwrap_or_default())
+    }
+    async fn set_attempts(
+        &mut self,
+        email: &Email,
+        attempts: LoginAttempts,
+    ) -> Result<(), LoginAttemptStoreError> {
+        self.attempts.insert(email.clone(), attempts);
+        Ok(())
+    }
+    async fn remove_attempts(&mut self, email: &Email) -> Result<(), LoginAttemptStoreError> {
+        self.attempts.remove(email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_attempts_should_default_to_none() {
+        let store = HashmapLoginAttemptStore::default();
+
+        let result = store.get_attempts(&email()).await;
+        assert_eq!(result, Ok(LoginAttempts::default()));
+    }
+
+    #[tokio::test]
+    async fn set_attempts_should_persist_and_remove_should_clear() {
+        let mut store = HashmapLoginAttemptStore::default();
+        let mut attempts = LoginAttempts::default();
+        attempts.record_failure(1, 5, 60);
+
+        store
+            .set_attempts(&email(), attempts.clone())
+            .await
+            .unwrap();
+        assert_eq!(store.get_attempts(&email()).await, Ok(attempts));
+
+        store.remove_attempts(&email()).await.unwrap();
+        assert_eq!(
+            store.get_attempts(&email()).await,
+            Ok(LoginAttempts::default())
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::data_stores::{
+    RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError,
+};
+
+#[derive(Default)]
+pub struct HashmapRefreshTokenStore {
+    tokens: HashMap<String, RefreshTokenRecord>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.tokens
+            .insert(token.as_ref().expose_secret().to_owned(), record);
+        Ok(())
+    }
+
+    async fn get_token(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        match self.tokens.get(token.as_ref().expose_secret()) {
+            Some(record) => Ok(record.clone()),
+            None => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn mark_token_used(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        match self.tokens.get_mut(token.as_ref().expose_secret()) {
+            Some(record) => {
+                record.used = true;
+                Ok(())
+            }
+            None => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        self.tokens
+            .retain(|_, record| record.family_id != family_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use crate::domain::Email;
+
+    use super::*;
+
+    fn record(family_id: &str) -> RefreshTokenRecord {
+        RefreshTokenRecord::new(
+            Email::parse(Secret::new("test@test.com".to_owned())).unwrap(),
+            "app-service".to_owned(),
+            family_id.to_owned(),
+            0,
+        )
+    }
+
+    #[tokio::test]
+    async fn get_token_should_return_added_record() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+
+        let result = store.add_token(token.clone(), record("family")).await;
+        assert_eq!(result, Ok(()));
+
+        let result = store.get_token(&token).await;
+        assert_eq!(result, Ok(record("family")));
+    }
+
+    #[tokio::test]
+    async fn get_token_should_return_not_found() {
+        let store = HashmapRefreshTokenStore::default();
+
+        let result = store.get_token(&RefreshToken::default()).await;
+        assert_eq!(result, Err(RefreshTokenStoreError::TokenNotFound));
+    }
+
+    #[tokio::test]
+    async fn mark_token_used_should_flag_record() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let token = RefreshToken::default();
+        store
+            .add_token(token.clone(), record("family"))
+            .await
+            .unwrap();
+
+        let result = store.mark_token_used(&token).await;
+        assert_eq!(result, Ok(()));
+
+        let result = store.get_token(&token).await.unwrap();
+        assert!(result.used);
+    }
+
+    #[tokio::test]
+    async fn revoke_family_should_only_remove_tokens_in_family() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let first = RefreshToken::default();
+        let second = RefreshToken::default();
+        let other = RefreshToken::default();
+        store
+            .add_token(first.clone(), record("family"))
+            .await
+            .unwrap();
+        store
+            .add_token(second.clone(), record("family"))
+            .await
+            .unwrap();
+        store
+            .add_token(other.clone(), record("other"))
+            .await
+            .unwrap();
+
+        let result = store.revoke_family("family").await;
+        assert_eq!(result, Ok(()));
+
+        assert_eq!(
+            store.get_token(&first).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+        assert_eq!(
+            store.get_token(&second).await,
+            Err(RefreshTokenStoreError::TokenNotFound)
+        );
+        assert!(store.get_token(&other).await.is_ok());
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{Session, SessionStore, SessionStoreError},
+    Email,
+};
+
+#[derive(Default)]
+pub struct HashmapSessionStore {
+    sessions: HashMap<String, Session>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        match self.sessions.get(id) {
+            Some(session) => Ok(session.clone()),
+            None => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| &session.email == email)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    async fn touch_session(
+        &mut self,
+        id: &str,
+        last_seen_at: u64,
+    ) -> Result<(), SessionStoreError> {
+        match self.sessions.get_mut(id) {
+            Some(session) => {
+                session.last_seen_at = last_seen_at;
+                Ok(())
+            }
+            None => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn delete_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        match self.sessions.remove(id) {
+            Some(_) => Ok(()),
+            None => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn session(email: &str, created_at: u64) -> Session {
+        Session::new(
+            Email::parse(Secret::new(email.to_owned())).unwrap(),
+            Some("Laptop".to_owned()),
+            Some("127.0.0.1".to_owned()),
+            Some("Firefox".to_owned()),
+            created_at,
+        )
+    }
+
+    #[tokio::test]
+    async fn get_session_should_return_added_session() {
+        let mut store = HashmapSessionStore::default();
+        let session = session("test@test.com", 100);
+
+        let result = store.add_session(session.clone()).await;
+        assert_eq!(result, Ok(()));
+
+        let result = store.get_session(&session.id).await;
+        assert_eq!(result, Ok(session));
+    }
+
+    #[tokio::test]
+    async fn get_session_should_return_not_found() {
+        let store = HashmapSessionStore::default();
+
+        let result = store.get_session("missing").await;
+        assert_eq!(result, Err(SessionStoreError::SessionNotFound));
+    }
+
+    #[tokio::test]
+    async fn get_sessions_should_only_return_users_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let second = session("test@test.com", 200);
+        let first = session("test@test.com", 100);
+        let other = session("other@test.com", 100);
+        store.add_session(second.clone()).await.unwrap();
+        store.add_session(first.clone()).await.unwrap();
+        store.add_session(other).await.unwrap();
+
+        let result = store
+            .get_sessions(&Email::parse(Secret::new("test@test.com".to_owned())).unwrap())
+            .await;
+        assert_eq!(result, Ok(vec![first, second]));
+    }
+
+    #[tokio::test]
+    async fn touch_session_should_update_last_seen() {
+        let mut store = HashmapSessionStore::default();
+        let session = session("test@test.com", 100);
+        store.add_session(session.clone()).await.unwrap();
+
+        let result = store.touch_session(&session.id, 500).await;
+        assert_eq!(result, Ok(()));
+
+        let result = store.get_session(&session.id).await.unwrap();
+        assert_eq!(result.last_seen_at, 500);
+        assert_eq!(result.created_at, 100);
+    }
+
+    #[tokio::test]
+    async fn delete_session_should_remove_session() {
+        let mut store = HashmapSessionStore::default();
+        let session = session("test@test.com", 100);
+        store.add_session(session.clone()).await.unwrap();
+
+        let result = store.delete_session(&session.id).await;
+        assert_eq!(result, Ok(()));
+
+        let result = store.get_session(&session.id).await;
+        assert_eq!(result, Err(SessionStoreError::SessionNotFound));
+
+        let result = store.delete_session(&session.id).await;
+        assert_eq!(result, Err(SessionStoreError::SessionNotFound));
+    }
+}
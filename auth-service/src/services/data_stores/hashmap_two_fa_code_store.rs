@@ -2,14 +2,27 @@ use std::collections::HashMap;
 
 use color_eyre::eyre::eyre;
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    Email,
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+        Email,
+    },
+    utils::auth::now_timestamp,
 };
 
+struct PendingCode {
+    email: Email,
+    code: TwoFACode,
+    audience: String,
+    // Wrong codes entered for the login attempt
+    failures: u32,
+    resends: u32,
+    sent_at: u64,
+}
+
 #[derive(Default)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: HashMap<LoginAttemptId, PendingCode>,
 }
 
 #[async_trait::async_trait]
@@ -19,38 +32,346 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
+        audience: String,
     ) -> Result<(), TwoFACodeStoreError> {
-        if self.codes.contains_key(&email) {
-            self.codes.remove(&email);
-        }
+        let pending = PendingCode {
+            email,
+            code,
+            audience,
+            failures: 0,
+            resends: 0,
+            sent_at: now_timestamp(),
+        };
 
-        match self.codes.insert(email, (login_attempt_id, code)) {
+        match self.codes.insert(login_attempt_id, pending) {
             Some(_) => Err(TwoFACodeStoreError::UnexpectedError(eyre!(
-                "failed to insert code into hashset two fa code store"
+                "login attempt id already in hashmap two fa code store"
             ))),
             None => Ok(()),
         }
     }
 
     async fn get_code(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode), TwoFACodeStoreError> {
+        match self.codes.get(login_attempt_id) {
+            Some(pending) => Ok((pending.email.to_owned(), pending.code.to_owned())),
+            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        }
+    }
+
+    async fn get_codes(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        match self.codes.get(email) {
-            Some((login_attempt_id, two_fa_code)) => {
-                Ok((login_attempt_id.to_owned(), two_fa_code.to_owned()))
-            }
+    ) -> Result<Vec<(LoginAttemptId, TwoFACode)>, TwoFACodeStoreError> {
+        Ok(self
+            .codes
+            .iter()
+            .filter(|(_, pending)| pending.email == *email)
+            .map(|(login_attempt_id, pending)| {
+                (login_attempt_id.to_owned(), pending.code.to_owned())
+            })
+            .collect())
+    }
+
+    async fn get_audience(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<String, TwoFACodeStoreError> {
+        match self.codes.get(login_attempt_id) {
+            Some(pending) => Ok(pending.audience.to_owned()),
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        match self.codes.remove(email) {
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        match self.codes.remove(login_attempt_id) {
             Some(_) => Ok(()),
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        self.codes.retain(|_, pending| pending.email != *email);
+
+        Ok(())
+    }
+
+    async fn record_failed_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let failures = match self.codes.get_mut(login_attempt_id) {
+            Some(pending) => {
+                pending.failures += 1;
+                pending.failures
+            }
+            None => return Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        };
+
+        if failures >= max_attempts {
+            self.codes.remove(login_attempt_id);
+            return Err(TwoFACodeStoreError::TooManyFailedAttempts);
+        }
+
+        Ok(())
+    }
+
+    async fn record_resend(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        cooldown_seconds: u64,
+        max_resends: u32,
+    ) -> Result<TwoFACode, TwoFACodeStoreError> {
+        let Some(pending) = self.codes.get_mut(login_attempt_id) else {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        };
+
+        if pending.resends >= max_resends {
+            return Err(TwoFACodeStoreError::TooManyResends);
+        }
+
+        let now = now_timestamp();
+
+        if now < pending.sent_at + cooldown_seconds {
+            return Err(TwoFACodeStoreError::ResendTooSoon);
+        }
+
+        pending.resends += 1;
+        pending.sent_at = now;
+
+        Ok(pending.code.clone())
+    }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@test.com".to_owned())).unwrap()
+    }
+
+    fn login_attempt_id() -> LoginAttemptId {
+        LoginAttemptId::parse(Secret::new(uuid::Uuid::new_v4().to_string())).unwrap()
+    }
+
+    fn audience() -> String {
+        "app-service".to_owned()
+    }
+
+    fn code(code: &str) -> TwoFACode {
+        TwoFACode::parse(Secret::new(code.to_owned())).unwrap()
+    }
+
+    async fn store_with_code(login_attempt_id: &LoginAttemptId) -> HashmapTwoFACodeStore {
+        let mut store = HashmapTwoFACodeStore::default();
+
+        store
+            .add_code(
+                email(),
+                login_attempt_id.clone(),
+                code("123456"),
+                audience(),
+            )
+            .await
+            .unwrap();
+
+        store
+    }
+
+    #[tokio::test]
+    async fn get_audience_should_return_audience_of_login_attempt() {
+        let login_attempt_id = login_attempt_id();
+        let store = store_with_code(&login_attempt_id).await;
+
+        assert_eq!(store.get_audience(&login_attempt_id).await, Ok(audience()));
+        assert_eq!(
+            store.get_audience(&self::login_attempt_id()).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_keep_concurrent_login_attempts_for_a_user() {
+        let first_attempt = login_attempt_id();
+        let second_attempt = login_attempt_id();
+        let mut store = store_with_code(&first_attempt).await;
+
+        store
+            .add_code(email(), second_attempt.clone(), code("654321"), audience())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_code(&first_attempt).await,
+            Ok((email(), code("123456")))
+        );
+        assert_eq!(
+            store.get_code(&second_attempt).await,
+            Ok((email(), code("654321")))
+        );
+        assert_eq!(store.get_codes(&email()).await.unwrap().len(), 2);
+
+        store.remove_code(&first_attempt).await.unwrap();
+
+        assert_eq!(
+            store.get_code(&first_attempt).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+        assert!(store.get_code(&second_attempt).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn remove_codes_should_only_remove_the_users_attempts() {
+        let other_email = Email::parse(Secret::new("other@test.com".to_owned())).unwrap();
+        let other_attempt = login_attempt_id();
+        let mut store = store_with_code(&login_attempt_id()).await;
+
+        store
+            .add_code(email(), login_attempt_id(), code("654321"), audience())
+            .await
+            .unwrap();
+        store
+            .add_code(
+                other_email.clone(),
+                other_attempt.clone(),
+                code("111111"),
+                audience(),
+            )
+            .await
+            .unwrap();
+
+        store.remove_codes(&email()).await.unwrap();
+
+        assert!(store.get_codes(&email()).await.unwrap().is_empty());
+        assert_eq!(
+            store.get_code(&other_attempt).await,
+            Ok((other_email, code("111111")))
+        );
+    }
+
+    #[tokio::test]
+    async fn record_failed_attempt_should_remove_code_at_limit() {
+        let login_attempt_id = login_attempt_id();
+        let mut store = store_with_code(&login_attempt_id).await;
+
+        for _ in 0..2 {
+            assert_eq!(
+                store.record_failed_attempt(&login_attempt_id, 3).await,
+                Ok(())
+            );
+        }
+
+        assert_eq!(
+            store.record_failed_attempt(&login_attempt_id, 3).await,
+            Err(TwoFACodeStoreError::TooManyFailedAttempts)
+        );
+        assert_eq!(
+            store.get_code(&login_attempt_id).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn failures_should_be_counted_per_login_attempt() {
+        let login_attempt_id = login_attempt_id();
+        let mut store = store_with_code(&login_attempt_id).await;
+
+        store
+            .record_failed_attempt(&login_attempt_id, 2)
+            .await
+            .unwrap();
+
+        let new_login_attempt_id = self::login_attempt_id();
+        store
+            .add_code(
+                email(),
+                new_login_attempt_id.clone(),
+                code("654321"),
+                audience(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.record_failed_attempt(&new_login_attempt_id, 2).await,
+            Ok(())
+        );
+        assert_eq!(
+            store.record_failed_attempt(&login_attempt_id, 2).await,
+            Err(TwoFACodeStoreError::TooManyFailedAttempts)
+        );
+        assert!(store.get_code(&new_login_attempt_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn record_failed_attempt_should_ignore_unknown_login_attempts() {
+        let login_attempt_id = login_attempt_id();
+        let mut store = store_with_code(&login_attempt_id).await;
+
+        assert_eq!(
+            store
+                .record_failed_attempt(&self::login_attempt_id(), 1)
+                .await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+        assert!(store.get_code(&login_attempt_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn record_resend_should_return_code_until_limit() {
+        let login_attempt_id = login_attempt_id();
+        let mut store = store_with_code(&login_attempt_id).await;
+
+        for _ in 0..2 {
+            assert_eq!(
+                store.record_resend(&login_attempt_id, 0, 2).await,
+                Ok(code("123456"))
+            );
+        }
+
+        assert_eq!(
+            store.record_resend(&login_attempt_id, 0, 2).await,
+            Err(TwoFACodeStoreError::TooManyResends)
+        );
+        // Running out of resends leaves the code usable
+        assert!(store.get_code(&login_attempt_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn record_resend_should_enforce_cooldown() {
+        let login_attempt_id = login_attempt_id();
+        let mut store = store_with_code(&login_attempt_id).await;
+
+        assert_eq!(
+            store.record_resend(&login_attempt_id, 60, 2).await,
+            Err(TwoFACodeStoreError::ResendTooSoon)
+        );
+
+        store.codes.get_mut(&login_attempt_id).unwrap().sent_at -= 60;
+
+        assert!(store.record_resend(&login_attempt_id, 60, 2).await.is_ok());
+        assert_eq!(
+            store.record_resend(&login_attempt_id, 60, 2).await,
+            Err(TwoFACodeStoreError::ResendTooSoon)
+        );
+    }
+
+    #[tokio::test]
+    async fn record_resend_should_ignore_unknown_login_attempts() {
+        let mut store = store_with_code(&login_attempt_id()).await;
+
+        assert_eq!(
+            store.record_resend(&login_attempt_id(), 0, 2).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+}
@@ -1,23 +1,37 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    email::Email,
-    password::Password,
-    user::User,
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        email::Email,
+        password::Password,
+        user::User,
+    },
+    utils::auth::now_timestamp,
 };
 
 #[derive(Default)]
 pub struct HashmapUserStore {
     users: HashMap<Email, User>,
+    recovery_codes: HashMap<Uuid, HashSet<String>>,
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        if let Ok(_) = self.get_user(&user.email).await {
+    async fn add_user(
+        &mut self,
+        user: User,
+        recovery_code_hashes: Vec<String>,
+    ) -> Result<(), UserStoreError> {
+        if self.get_user(&user.email).await.is_ok() {
             Err(UserStoreError::UserAlreadyExists)
         } else {
+            if !recovery_code_hashes.is_empty() {
+                self.recovery_codes
+                    .insert(user.id, recovery_code_hashes.into_iter().collect());
+            }
             self.users.insert(user.email.clone(), user);
             Ok(())
         }
@@ -44,6 +58,123 @@ impl UserStore for HashmapUserStore {
             Err(UserStoreError::UserNotFound)
         }
     }
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.email_verified = true;
+                user.updated_at = now_timestamp();
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+    async fn record_login(
+        &mut self,
+        email: &Email,
+        logged_in_at: u64,
+    ) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.last_login_at = Some(logged_in_at);
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+    async fn update_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        match self.users.values_mut().find(|stored| stored.id == user.id) {
+            Some(stored) => {
+                *stored = User {
+                    email: stored.email.clone(),
+                    password: stored.password.clone(),
+                    created_at: stored.created_at,
+                    updated_at: now_timestamp(),
+                    ..user
+                };
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(user) => {
+                user.password = password;
+                user.updated_at = now_timestamp();
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+    async fn update_email(
+        &mut self,
+        email: &Email,
+        new_email: Email,
+    ) -> Result<(), UserStoreError> {
+        if self.users.contains_key(&new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+
+        match self.users.remove(email) {
+            Some(user) => {
+                self.users.insert(
+                    new_email.clone(),
+                    User {
+                        email: new_email,
+                        updated_at: now_timestamp(),
+                        ..user
+                    },
+                );
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.users.remove(email) {
+            Some(user) => {
+                self.recovery_codes.remove(&user.id);
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+    async fn replace_recovery_codes(
+        &mut self,
+        user_id: Uuid,
+        code_hashes: Vec<String>,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.values().any(|user| user.id == user_id) {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        self.recovery_codes
+            .insert(user_id, code_hashes.into_iter().collect());
+        Ok(())
+    }
+    async fn use_recovery_code(
+        &mut self,
+        user_id: Uuid,
+        code_hash: &str,
+    ) -> Result<(), UserStoreError> {
+        let removed = self
+            .recovery_codes
+            .get_mut(&user_id)
+            .is_some_and(|codes| codes.remove(code_hash));
+
+        if removed {
+            Ok(())
+        } else {
+            Err(UserStoreError::InvalidCredentials)
+        }
+    }
+    async fn count_recovery_codes(&self, user_id: Uuid) -> Result<usize, UserStoreError> {
+        Ok(self.recovery_codes.get(&user_id).map_or(0, HashSet::len))
+    }
 }
 
 #[cfg(test)]
@@ -51,6 +182,7 @@ mod tests {
     use secrecy::Secret;
 
     use super::*;
+    use crate::domain::user::UserStatus;
 
     #[tokio::test]
     async fn add_user_should_succeed() {
@@ -60,7 +192,7 @@ mod tests {
             Password::parse(Secret::new("test123456".to_owned())).unwrap(),
             false,
         );
-        let result = user_store.add_user(user).await;
+        let result = user_store.add_user(user, vec![]).await;
         assert_eq!(result, Ok(()));
     }
 
@@ -72,7 +204,7 @@ mod tests {
             Password::parse(Secret::new("test123456".to_owned())).unwrap(),
             false,
         );
-        let result = user_store.add_user(user).await;
+        let result = user_store.add_user(user, vec![]).await;
         assert_eq!(result, Ok(()));
 
         let user2 = User::new(
@@ -80,7 +212,7 @@ mod tests {
             Password::parse(Secret::new("abc123456".to_owned())).unwrap(),
             true,
         );
-        let result2 = user_store.add_user(user2).await;
+        let result2 = user_store.add_user(user2, vec![]).await;
         assert_eq!(result2, Err(UserStoreError::UserAlreadyExists));
     }
 
@@ -103,7 +235,7 @@ mod tests {
             Password::parse(Secret::new("abc123456".to_owned())).unwrap(),
             true,
         );
-        if let Ok(_) = user_store.add_user(user.clone()).await {
+        if user_store.add_user(user.clone(), vec![]).await.is_ok() {
             let result = user_store.get_user(&Email::parse(email).unwrap()).await;
             assert_eq!(result, Ok(user));
         }
@@ -119,7 +251,7 @@ mod tests {
             Password::parse(password.clone()).unwrap(),
             false,
         );
-        if let Ok(_) = user_store.add_user(user).await {
+        if user_store.add_user(user, vec![]).await.is_ok() {
             let result = user_store
                 .validate_user(
                     &Email::parse(email).unwrap(),
@@ -140,7 +272,7 @@ mod tests {
             Password::parse(password).unwrap(),
             true,
         );
-        if let Ok(_) = user_store.add_user(user).await {
+        if user_store.add_user(user, vec![]).await.is_ok() {
             let result = user_store
                 .validate_user(
                     &Email::parse(email).unwrap(),
@@ -161,7 +293,7 @@ mod tests {
             Password::parse(password.clone()).unwrap(),
             true,
         );
-        if let Ok(_) = user_store.add_user(user).await {
+        if user_store.add_user(user, vec![]).await.is_ok() {
             let result = user_store
                 .validate_user(
                     &Email::parse(Secret::new("abc@test.com".to_owned())).unwrap(),
@@ -171,4 +303,290 @@ mod tests {
             assert_eq!(result, Err(UserStoreError::UserNotFound));
         }
     }
+
+    #[tokio::test]
+    async fn mark_email_verified_should_verify_user() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let user = User::new(
+            email.clone(),
+            Password::parse(Secret::new("abc123456".to_owned())).unwrap(),
+            false,
+        );
+        user_store.add_user(user, vec![]).await.unwrap();
+        assert!(!user_store.get_user(&email).await.unwrap().email_verified);
+
+        let result = user_store.mark_email_verified(&email).await;
+        assert_eq!(result, Ok(()));
+        assert!(user_store.get_user(&email).await.unwrap().email_verified);
+    }
+
+    #[tokio::test]
+    async fn mark_email_verified_should_return_user_not_found_error() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+
+        let result = user_store.mark_email_verified(&email).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn record_login_should_set_last_login() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let user = User::new(
+            email.clone(),
+            Password::parse(Secret::new("abc123456".to_owned())).unwrap(),
+            false,
+        );
+        user_store.add_user(user, vec![]).await.unwrap();
+        assert_eq!(
+            user_store.get_user(&email).await.unwrap().last_login_at,
+            None
+        );
+
+        let result = user_store.record_login(&email, 1_700_000_000).await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            user_store.get_user(&email).await.unwrap().last_login_at,
+            Some(1_700_000_000)
+        );
+    }
+
+    #[tokio::test]
+    async fn update_password_should_replace_password() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let old_password = Password::parse(Secret::new("abc123456".to_owned())).unwrap();
+        let new_password = Password::parse(Secret::new("xyz987654".to_owned())).unwrap();
+        let user = User::new(email.clone(), old_password.clone(), false);
+        user_store.add_user(user, vec![]).await.unwrap();
+
+        let result = user_store
+            .update_password(&email, new_password.clone())
+            .await;
+        assert_eq!(result, Ok(()));
+
+        assert_eq!(
+            user_store.validate_user(&email, &old_password).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert_eq!(
+            user_store.validate_user(&email, &new_password).await,
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn update_password_should_return_user_not_found_error() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("abc123456".to_owned())).unwrap();
+
+        let result = user_store.update_password(&email, password).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn update_user_should_persist_changes_but_not_password() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("abc123456".to_owned())).unwrap();
+        let user = User::new(email.clone(), password.clone(), false);
+        user_store.add_user(user, vec![]).await.unwrap();
+
+        let mut updated = user_store.get_user(&email).await.unwrap();
+        updated.password = Password::parse(Secret::new("xyz987654".to_owned())).unwrap();
+        updated.requires_2fa = true;
+        updated.email_verified = true;
+
+        let result = user_store.update_user(updated).await;
+        assert_eq!(result, Ok(()));
+
+        let stored = user_store.get_user(&email).await.unwrap();
+        assert!(stored.requires_2fa);
+        assert!(stored.email_verified);
+        assert_eq!(user_store.validate_user(&email, &password).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn update_user_should_persist_status() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("abc123456".to_owned())).unwrap();
+        let user = User::new(email.clone(), password, false);
+        user_store.add_user(user, vec![]).await.unwrap();
+
+        let mut updated = user_store.get_user(&email).await.unwrap();
+        updated.status = UserStatus::Suspended;
+        updated.status_reason = Some("Chargeback".to_owned());
+        updated.status_changed_at = Some(1_700_000_000);
+
+        assert_eq!(user_store.update_user(updated).await, Ok(()));
+
+        let stored = user_store.get_user(&email).await.unwrap();
+        assert_eq!(stored.status, UserStatus::Suspended);
+        assert_eq!(stored.status_reason.as_deref(), Some("Chargeback"));
+        assert_eq!(stored.status_changed_at, Some(1_700_000_000));
+    }
+
+    #[tokio::test]
+    async fn update_user_should_return_user_not_found_error() {
+        let mut user_store = HashmapUserStore::default();
+        let user = User::new(
+            Email::parse(Secret::new("test@test.com".to_owned())).unwrap(),
+            Password::parse(Secret::new("abc123456".to_owned())).unwrap(),
+            true,
+        );
+
+        let result = user_store.update_user(user).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn delete_user_should_remove_user() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let user = User::new(
+            email.clone(),
+            Password::parse(Secret::new("abc123456".to_owned())).unwrap(),
+            false,
+        );
+        user_store.add_user(user, vec![]).await.unwrap();
+
+        let result = user_store.delete_user(&email).await;
+        assert_eq!(result, Ok(()));
+
+        let result = user_store.get_user(&email).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn delete_user_should_return_user_not_found_error() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+
+        let result = user_store.delete_user(&email).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn update_email_should_move_user() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let new_email = Email::parse(Secret::new("new@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("abc123456".to_owned())).unwrap();
+        let user = User::new(email.clone(), password.clone(), true);
+        let id = user.id;
+        user_store.add_user(user, vec![]).await.unwrap();
+
+        let result = user_store.update_email(&email, new_email.clone()).await;
+        assert_eq!(result, Ok(()));
+
+        assert_eq!(
+            user_store.get_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        let moved = user_store.get_user(&new_email).await.unwrap();
+        assert_eq!(moved.email, new_email);
+        assert_eq!(moved.id, id);
+        assert!(moved.requires_2fa);
+        assert_eq!(
+            user_store.validate_user(&new_email, &password).await,
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn update_email_should_return_error_for_taken_email() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let new_email = Email::parse(Secret::new("new@test.com".to_owned())).unwrap();
+        for email in [&email, &new_email] {
+            let user = User::new(
+                email.clone(),
+                Password::parse(Secret::new("abc123456".to_owned())).unwrap(),
+                false,
+            );
+            user_store.add_user(user, vec![]).await.unwrap();
+        }
+
+        let result = user_store.update_email(&email, new_email).await;
+        assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
+    }
+
+    #[tokio::test]
+    async fn add_user_should_store_recovery_codes() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("abc123456".to_owned())).unwrap();
+        let user = User::new(email, password, true);
+        let user_id = user.id;
+        user_store
+            .add_user(user, vec!["a".to_owned(), "b".to_owned()])
+            .await
+            .unwrap();
+
+        assert_eq!(user_store.count_recovery_codes(user_id).await, Ok(2));
+        assert_eq!(user_store.use_recovery_code(user_id, "a").await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn recovery_codes_should_only_work_once() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("abc123456".to_owned())).unwrap();
+        let user = User::new(email, password, true);
+        let user_id = user.id;
+        user_store.add_user(user, vec![]).await.unwrap();
+
+        let hashes = vec!["a".to_owned(), "b".to_owned()];
+        assert_eq!(
+            user_store.replace_recovery_codes(user_id, hashes).await,
+            Ok(())
+        );
+        assert_eq!(user_store.count_recovery_codes(user_id).await, Ok(2));
+
+        assert_eq!(user_store.use_recovery_code(user_id, "a").await, Ok(()));
+        assert_eq!(
+            user_store.use_recovery_code(user_id, "a").await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert_eq!(user_store.count_recovery_codes(user_id).await, Ok(1));
+    }
+
+    #[tokio::test]
+    async fn replace_recovery_codes_should_discard_old_codes() {
+        let mut user_store = HashmapUserStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("abc123456".to_owned())).unwrap();
+        let user = User::new(email, password, true);
+        let user_id = user.id;
+        user_store.add_user(user, vec![]).await.unwrap();
+
+        user_store
+            .replace_recovery_codes(user_id, vec!["a".to_owned()])
+            .await
+            .unwrap();
+        user_store
+            .replace_recovery_codes(user_id, vec!["b".to_owned()])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            user_store.use_recovery_code(user_id, "a").await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert_eq!(user_store.use_recovery_code(user_id, "b").await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn replace_recovery_codes_should_return_user_not_found_error() {
+        let mut user_store = HashmapUserStore::default();
+
+        let result = user_store
+            .replace_recovery_codes(Uuid::new_v4(), vec!["a".to_owned()])
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
 }
@@ -1,19 +1,23 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use color_eyre::eyre::eyre;
-use secrecy::{ExposeSecret, Secret};
 
 use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
 
 #[derive(Default)]
 pub struct HashsetBannedTokenStore {
     pub tokens: HashSet<String>,
+    pub revocation_epochs: HashMap<String, u64>,
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        if self.tokens.insert(token.expose_secret().to_owned()) == true {
+    async fn add_token(
+        &mut self,
+        jti: String,
+        _expires_at: u64,
+    ) -> Result<(), BannedTokenStoreError> {
+        if self.tokens.insert(jti) {
             Ok(())
         } else {
             Err(BannedTokenStoreError::UnexpectedError(eyre!(
@@ -22,11 +26,28 @@ impl BannedTokenStore for HashsetBannedTokenStore {
         }
     }
 
-    async fn contains_token(&self, token: Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        match self.tokens.get(token.expose_secret()) {
-            Some(_) => Ok(true),
-            None => Ok(false),
-        }
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        Ok(self.tokens.contains(jti))
+    }
+
+    async fn revoke_all_tokens(
+        &mut self,
+        subject: &str,
+        revoked_at: u64,
+    ) -> Result<(), BannedTokenStoreError> {
+        let epoch = self
+            .revocation_epochs
+            .entry(subject.to_owned())
+            .or_default();
+        *epoch = (*epoch).max(revoked_at);
+        Ok(())
+    }
+
+    async fn get_revocation_epoch(
+        &self,
+        subject: &str,
+    ) -> Result<Option<u64>, BannedTokenStoreError> {
+        Ok(self.revocation_epochs.get(subject).copied())
     }
 }
 
@@ -38,7 +59,7 @@ mod tests {
     async fn add_token_should_succeed() {
         let mut banned_token_store = HashsetBannedTokenStore::default();
         let result = banned_token_store
-            .add_token(Secret::new("TestToken".to_string()))
+            .add_token("TestToken".to_string(), 0)
             .await;
         assert_eq!(result, Ok(()))
     }
@@ -47,14 +68,11 @@ mod tests {
     async fn get_token_should_return_true_if_token_exists() {
         let mut banned_token_store = HashsetBannedTokenStore::default();
         let result = banned_token_store
-            .add_token(Secret::new("TestToken".to_string()))
-            .await
-            .unwrap();
-        assert_eq!(result, ());
-
-        let result = banned_token_store
-            .contains_token(Secret::new("TestToken".to_string()))
+            .add_token("TestToken".to_string(), 0)
             .await;
+        assert_eq!(result, Ok(()));
+
+        let result = banned_token_store.contains_token("TestToken").await;
         assert_eq!(result, Ok(true));
     }
 
@@ -62,9 +80,35 @@ mod tests {
     async fn get_token_should_return_false_if_token_does_not_exist() {
         let banned_token_store = HashsetBannedTokenStore::default();
 
+        let result = banned_token_store.contains_token("TestToken").await;
+        assert_eq!(result, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn get_revocation_epoch_should_return_none_if_never_revoked() {
+        let banned_token_store = HashsetBannedTokenStore::default();
+
         let result = banned_token_store
-            .contains_token(Secret::new("TestToken".to_string()))
+            .get_revocation_epoch("test@example.com")
             .await;
-        assert_eq!(result, Ok(false));
+        assert_eq!(result, Ok(None));
+    }
+
+    #[tokio::test]
+    async fn revoke_all_tokens_should_never_move_epoch_backwards() {
+        let mut banned_token_store = HashsetBannedTokenStore::default();
+        banned_token_store
+            .revoke_all_tokens("test@example.com", 200)
+            .await
+            .unwrap();
+        banned_token_store
+            .revoke_all_tokens("test@example.com", 100)
+            .await
+            .unwrap();
+
+        let result = banned_token_store
+            .get_revocation_epoch("test@example.com")
+            .await;
+        assert_eq!(result, Ok(Some(200)));
     }
 }
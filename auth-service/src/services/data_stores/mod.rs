@@ -1,6 +1,14 @@
+pub mod hashmap_login_attempt_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_session_store;
 pub mod hashmap_two_fa_code_store;
 pub mod hashmap_user_store;
 pub mod hashset_banned_token_store;
+pub mod postgres_banned_token_store;
+pub mod postgres_session_store;
 pub mod postgres_user_store;
 pub mod redis_banned_token_store;
+pub mod redis_login_attempt_store;
+pub mod redis_refresh_token_store;
+pub mod redis_session_store;
 pub mod redis_two_fa_code_store;
@@ -0,0 +1,110 @@
+use color_eyre::eyre::Context;
+use sqlx::PgPool;
+
+use crate::{
+    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
+    utils::auth::now_timestamp,
+};
+
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to PostgreSQL", skip_all)]
+    async fn add_token(
+        &mut self,
+        jti: String,
+        expires_at: u64,
+    ) -> Result<(), BannedTokenStoreError> {
+        let expires_at = to_i64(expires_at)?;
+
+        // Expired tokens are rejected anyway, so there is no need to keep them banned
+        sqlx::query!(
+            "DELETE FROM banned_tokens WHERE expires_at <= $1",
+            to_i64(now_timestamp())?
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            "INSERT INTO banned_tokens (jti, expires_at) VALUES ($1, $2) ON CONFLICT (jti) DO NOTHING",
+            jti,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Checking for banned token in PostgreSQL", skip_all)]
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        let is_banned = sqlx::query!("SELECT jti FROM banned_tokens WHERE jti = $1", jti)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?
+            .is_some();
+
+        Ok(is_banned)
+    }
+    #[tracing::instrument(name = "Revoking all tokens in PostgreSQL", skip_all)]
+    async fn revoke_all_tokens(
+        &mut self,
+        subject: &str,
+        revoked_at: u64,
+    ) -> Result<(), BannedTokenStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO token_revocations (subject, revoked_at) VALUES ($1, $2)
+            ON CONFLICT (subject) DO UPDATE
+            SET revoked_at = GREATEST(token_revocations.revoked_at, EXCLUDED.revoked_at)
+            "#,
+            subject,
+            to_i64(revoked_at)?
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Getting revocation epoch from PostgreSQL", skip_all)]
+    async fn get_revocation_epoch(
+        &self,
+        subject: &str,
+    ) -> Result<Option<u64>, BannedTokenStoreError> {
+        let revoked_at = sqlx::query!(
+            "SELECT revoked_at FROM token_revocations WHERE subject = $1",
+            subject
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?
+        .map(|row| row.revoked_at);
+
+        revoked_at
+            .map(|revoked_at| {
+                revoked_at
+                    .try_into()
+                    .wrap_err("failed to cast revoked_at to u64")
+                    .map_err(BannedTokenStoreError::UnexpectedError)
+            })
+            .transpose()
+    }
+}
+
+fn to_i64(timestamp: u64) -> Result<i64, BannedTokenStoreError> {
+    timestamp
+        .try_into()
+        .wrap_err("failed to cast timestamp to i64")
+        .map_err(BannedTokenStoreError::UnexpectedError)
+}
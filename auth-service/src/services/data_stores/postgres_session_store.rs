@@ -0,0 +1,152 @@
+use color_eyre::eyre::{eyre, Context};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{Session, SessionStore, SessionStoreError},
+    Email,
+};
+
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    #[tracing::instrument(name = "Adding session to PostgreSQL", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, email, device, ip, user_agent, created_at, last_seen_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            session.id,
+            session.email.as_ref().expose_secret(),
+            session.device,
+            session.ip,
+            session.user_agent,
+            to_i64(session.created_at)?,
+            to_i64(session.last_seen_at)?
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Retrieving session from PostgreSQL", skip_all)]
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        sqlx::query_as!(
+            PostgresSession,
+            r#"
+            SELECT id, email, device, ip, user_agent, created_at, last_seen_at
+            FROM sessions WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?
+        .ok_or(SessionStoreError::SessionNotFound)?
+        .try_into()
+    }
+    #[tracing::instrument(name = "Retrieving user's sessions from PostgreSQL", skip_all)]
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        sqlx::query_as!(
+            PostgresSession,
+            r#"
+            SELECT id, email, device, ip, user_agent, created_at, last_seen_at
+            FROM sessions WHERE email = $1 ORDER BY created_at
+            "#,
+            email.as_ref().expose_secret()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(Session::try_from)
+        .collect()
+    }
+    #[tracing::instrument(name = "Updating session in PostgreSQL", skip_all)]
+    async fn touch_session(
+        &mut self,
+        id: &str,
+        last_seen_at: u64,
+    ) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            "UPDATE sessions SET last_seen_at = $1 WHERE id = $2",
+            to_i64(last_seen_at)?,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Deleting session from PostgreSQL", skip_all)]
+    async fn delete_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!("DELETE FROM sessions WHERE id = $1", id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct PostgresSession {
+    id: String,
+    email: String,
+    device: Option<String>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    created_at: i64,
+    last_seen_at: i64,
+}
+
+impl TryFrom<PostgresSession> for Session {
+    type Error = SessionStoreError;
+
+    fn try_from(session: PostgresSession) -> Result<Self, Self::Error> {
+        Ok(Session {
+            id: session.id,
+            email: Email::parse(Secret::new(session.email))
+                .map_err(|e| SessionStoreError::UnexpectedError(eyre!(e)))?,
+            device: session.device,
+            ip: session.ip,
+            user_agent: session.user_agent,
+            created_at: session
+                .created_at
+                .try_into()
+                .wrap_err("failed to cast created_at to u64")
+                .map_err(SessionStoreError::UnexpectedError)?,
+            last_seen_at: session
+                .last_seen_at
+                .try_into()
+                .wrap_err("failed to cast last_seen_at to u64")
+                .map_err(SessionStoreError::UnexpectedError)?,
+        })
+    }
+}
+
+fn to_i64(timestamp: u64) -> Result<i64, SessionStoreError> {
+    timestamp
+        .try_into()
+        .wrap_err("failed to cast timestamp to i64")
+        .map_err(SessionStoreError::UnexpectedError)
+}
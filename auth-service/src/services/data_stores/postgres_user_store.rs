@@ -5,11 +5,19 @@ use argon2::{
 use color_eyre::eyre::{eyre, Context, Result};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
+use uuid::Uuid;
 
-use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    user::User,
-    Email, Password,
+use crate::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        user::{TwoFAMethod, User, UserStatus},
+        DisplayName, Email, Password, TotpSecret,
+    },
+    utils::{
+        auth::now_timestamp,
+        constants::TOTP_ENCRYPTION_KEY,
+        encryption::{decrypt, encrypt},
+    },
 };
 
 pub struct PostgresUserStore {
@@ -25,55 +33,99 @@ impl PostgresUserStore {
 #[async_trait::async_trait]
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        match sqlx::query!(
+    async fn add_user(
+        &mut self,
+        user: User,
+        recovery_code_hashes: Vec<String>,
+    ) -> Result<(), UserStoreError> {
+        if sqlx::query!(
             "SELECT * FROM users WHERE email = $1 LIMIT 1",
             user.email.as_ref().expose_secret()
         )
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .is_some()
         {
-            Some(_) => return Err(UserStoreError::UserAlreadyExists),
-            None => (),
+            return Err(UserStoreError::UserAlreadyExists);
         }
 
         let hashed_password = compute_password_hash(user.password.as_ref().to_owned())
             .await
             .map_err(UserStoreError::UnexpectedError)?;
 
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
         sqlx::query!(
-            "INSERT INTO users (email, password_hash, requires_2fa) VALUES ($1, $2, $3)",
+            r#"
+            INSERT INTO users (
+                id, email, password_hash, requires_2fa, two_fa_method, totp_secret,
+                pending_totp_secret, totp_last_used_step, email_verified, display_name,
+                status, status_reason, status_changed_at, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            "#,
+            user.id,
             user.email.as_ref().expose_secret(),
             &hashed_password.expose_secret(),
-            user.requires_2fa
+            user.requires_2fa,
+            user.two_fa_method.as_ref(),
+            encrypt_totp_secret(user.totp_secret.as_ref(), user.id)?,
+            encrypt_totp_secret(user.pending_totp_secret.as_ref(), user.id)?,
+            user.totp_last_used_step.map(to_i64).transpose()?,
+            user.email_verified,
+            user.display_name.as_ref().map(AsRef::as_ref),
+            user.status.as_ref(),
+            user.status_reason.as_deref(),
+            user.status_changed_at.map(to_i64).transpose()?,
+            to_i64(user.created_at)?,
+            to_i64(user.updated_at)?
         )
-        .execute(&self.pool)
+        .execute(&mut *transaction)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-        Ok(())
+        // An empty list inserts nothing
+        sqlx::query!(
+            r#"
+            INSERT INTO recovery_codes (user_id, code_hash, created_at)
+            SELECT $1, code_hash, $3 FROM UNNEST($2::TEXT[]) AS code_hash
+            "#,
+            user.id,
+            &recovery_code_hashes,
+            to_i64(now_timestamp())?
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
     }
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        let postgres_user = sqlx::query_as!(
+        sqlx::query_as!(
             PostgresUser,
-            "SELECT email, password_hash, requires_2fa FROM users WHERE email = $1",
+            r#"
+            SELECT
+                id, email, password_hash, requires_2fa, two_fa_method, totp_secret,
+                pending_totp_secret, totp_last_used_step, email_verified, display_name,
+                status, status_reason, status_changed_at, created_at, updated_at, last_login_at
+            FROM users WHERE email = $1
+            "#,
             email.as_ref().expose_secret()
         )
-        .fetch_one(&self.pool)
+        .fetch_optional(&self.pool)
         .await
-        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
-
-        let user = User::new(
-            Email::parse(Secret::new(postgres_user.email))
-                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-            Password::parse(Secret::new(postgres_user.password_hash))
-                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-            postgres_user.requires_2fa,
-        );
-
-        Ok(user)
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?
+        .try_into()
     }
     #[tracing::instrument(name = "Validating user credentials in PostgreSQL", skip_all)]
     async fn validate_user(
@@ -90,12 +142,335 @@ impl UserStore for PostgresUserStore {
         .await
         .map_err(|_| UserStoreError::InvalidCredentials)
     }
+    #[tracing::instrument(name = "Marking email verified in PostgreSQL", skip_all)]
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET email_verified = TRUE, updated_at = $1 WHERE email = $2",
+            to_i64(now_timestamp())?,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Recording login in PostgreSQL", skip_all)]
+    async fn record_login(
+        &mut self,
+        email: &Email,
+        logged_in_at: u64,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET last_login_at = $1 WHERE email = $2",
+            to_i64(logged_in_at)?,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Updating user in PostgreSQL", skip_all)]
+    async fn update_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET requires_2fa = $1, two_fa_method = $2, totp_secret = $3,
+                pending_totp_secret = $4, totp_last_used_step = $5, email_verified = $6,
+                display_name = $7, status = $8, status_reason = $9, status_changed_at = $10,
+                last_login_at = $11, updated_at = $12
+            WHERE id = $13
+            "#,
+            user.requires_2fa,
+            user.two_fa_method.as_ref(),
+            encrypt_totp_secret(user.totp_secret.as_ref(), user.id)?,
+            encrypt_totp_secret(user.pending_totp_secret.as_ref(), user.id)?,
+            user.totp_last_used_step.map(to_i64).transpose()?,
+            user.email_verified,
+            user.display_name.as_ref().map(AsRef::as_ref),
+            user.status.as_ref(),
+            user.status_reason.as_deref(),
+            user.status_changed_at.map(to_i64).transpose()?,
+            user.last_login_at.map(to_i64).transpose()?,
+            to_i64(now_timestamp())?,
+            user.id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Updating password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let hashed_password = compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            "UPDATE users SET password_hash = $1, updated_at = $2 WHERE email = $3",
+            hashed_password.expose_secret(),
+            to_i64(now_timestamp())?,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Updating email in PostgreSQL", skip_all)]
+    async fn update_email(
+        &mut self,
+        email: &Email,
+        new_email: Email,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET email = $1, updated_at = $2 WHERE email = $3",
+            new_email.as_ref().expose_secret(),
+            to_i64(now_timestamp())?,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM users WHERE email = $1",
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Replacing recovery codes in PostgreSQL", skip_all)]
+    async fn replace_recovery_codes(
+        &mut self,
+        user_id: Uuid,
+        code_hashes: Vec<String>,
+    ) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+            .fetch_optional(&mut *transaction)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+            .is_none()
+        {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO recovery_codes (user_id, code_hash, created_at)
+            SELECT $1, code_hash, $3 FROM UNNEST($2::TEXT[]) AS code_hash
+            "#,
+            user_id,
+            &code_hashes,
+            to_i64(now_timestamp())?
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+    #[tracing::instrument(name = "Using recovery code in PostgreSQL", skip_all)]
+    async fn use_recovery_code(
+        &mut self,
+        user_id: Uuid,
+        code_hash: &str,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM recovery_codes WHERE user_id = $1 AND code_hash = $2",
+            user_id,
+            code_hash
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Counting recovery codes in PostgreSQL", skip_all)]
+    async fn count_recovery_codes(&self, user_id: Uuid) -> Result<usize, UserStoreError> {
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM recovery_codes WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        count
+            .try_into()
+            .wrap_err("failed to cast recovery code count to usize")
+            .map_err(UserStoreError::UnexpectedError)
+    }
 }
 
 struct PostgresUser {
+    id: Uuid,
     email: String,
     password_hash: String,
     requires_2fa: bool,
+    two_fa_method: String,
+    totp_secret: Option<String>,
+    pending_totp_secret: Option<String>,
+    totp_last_used_step: Option<i64>,
+    email_verified: bool,
+    display_name: Option<String>,
+    status: String,
+    status_reason: Option<String>,
+    status_changed_at: Option<i64>,
+    created_at: i64,
+    updated_at: i64,
+    last_login_at: Option<i64>,
+}
+
+impl TryFrom<PostgresUser> for User {
+    type Error = UserStoreError;
+
+    fn try_from(user: PostgresUser) -> Result<Self, Self::Error> {
+        Ok(User {
+            id: user.id,
+            email: Email::parse(Secret::new(user.email))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            password: Password::parse(Secret::new(user.password_hash))
+                .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+            requires_2fa: user.requires_2fa,
+            two_fa_method: TwoFAMethod::parse(&user.two_fa_method)
+                .map_err(UserStoreError::UnexpectedError)?,
+            totp_secret: decrypt_totp_secret(user.totp_secret, user.id)?,
+            pending_totp_secret: decrypt_totp_secret(user.pending_totp_secret, user.id)?,
+            totp_last_used_step: user
+                .totp_last_used_step
+                .map(u64::try_from)
+                .transpose()
+                .wrap_err("failed to cast totp_last_used_step to u64")
+                .map_err(UserStoreError::UnexpectedError)?,
+            email_verified: user.email_verified,
+            display_name: user
+                .display_name
+                .map(DisplayName::parse)
+                .transpose()
+                .map_err(UserStoreError::UnexpectedError)?,
+            status: UserStatus::parse(&user.status).map_err(UserStoreError::UnexpectedError)?,
+            status_reason: user.status_reason,
+            status_changed_at: user
+                .status_changed_at
+                .map(u64::try_from)
+                .transpose()
+                .wrap_err("failed to cast status_changed_at to u64")
+                .map_err(UserStoreError::UnexpectedError)?,
+            created_at: user
+                .created_at
+                .try_into()
+                .wrap_err("failed to cast created_at to u64")
+                .map_err(UserStoreError::UnexpectedError)?,
+            updated_at: user
+                .updated_at
+                .try_into()
+                .wrap_err("failed to cast updated_at to u64")
+                .map_err(UserStoreError::UnexpectedError)?,
+            last_login_at: user
+                .last_login_at
+                .map(u64::try_from)
+                .transpose()
+                .wrap_err("failed to cast last_login_at to u64")
+                .map_err(UserStoreError::UnexpectedError)?,
+        })
+    }
+}
+
+// The id is bound to the ciphertext, so a secret copied onto another row cannot be decrypted
+fn encrypt_totp_secret(
+    secret: Option<&TotpSecret>,
+    user_id: Uuid,
+) -> Result<Option<String>, UserStoreError> {
+    secret
+        .map(|secret| encrypt(&TOTP_ENCRYPTION_KEY, secret.as_ref(), user_id.as_bytes()))
+        .transpose()
+        .wrap_err("failed to encrypt TOTP secret")
+        .map_err(UserStoreError::UnexpectedError)
+}
+
+fn decrypt_totp_secret(
+    ciphertext: Option<String>,
+    user_id: Uuid,
+) -> Result<Option<TotpSecret>, UserStoreError> {
+    ciphertext
+        .map(|ciphertext| {
+            decrypt(&TOTP_ENCRYPTION_KEY, &ciphertext, user_id.as_bytes())
+                .and_then(TotpSecret::parse)
+        })
+        .transpose()
+        .wrap_err("failed to decrypt TOTP secret")
+        .map_err(UserStoreError::UnexpectedError)
+}
+
+fn to_i64(timestamp: u64) -> Result<i64, UserStoreError> {
+    timestamp
+        .try_into()
+        .wrap_err("failed to cast timestamp to i64")
+        .map_err(UserStoreError::UnexpectedError)
 }
 
 #[tracing::instrument(name = "Verify password hash", skip_all)]
@@ -107,7 +482,7 @@ async fn verify_password_hash(
     let result = tokio::task::spawn_blocking(move || {
         current_span.in_scope(|| {
             let expected_password_hash: PasswordHash<'_> =
-                PasswordHash::new(&expected_password_hash.expose_secret())?;
+                PasswordHash::new(expected_password_hash.expose_secret())?;
 
             Argon2::default()
                 .verify_password(
@@ -2,12 +2,11 @@ use std::sync::Arc;
 
 use color_eyre::eyre::Context;
 use redis::{Commands, Connection};
-use secrecy::{ExposeSecret, Secret};
 use tokio::sync::RwLock;
 
 use crate::{
     domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    utils::auth::TOKEN_TTL_SECONDS,
+    utils::auth::now_timestamp,
 };
 
 pub struct RedisBannedTokenStore {
@@ -23,15 +22,17 @@ impl RedisBannedTokenStore {
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Adding banned token to Redis", skip_all)]
-    async fn add_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError> {
-        let token_key = get_key(token.expose_secret());
+    async fn add_token(
+        &mut self,
+        jti: String,
+        expires_at: u64,
+    ) -> Result<(), BannedTokenStoreError> {
+        let token_key = get_key(&jti);
 
         let value = true;
 
-        let ttl: u64 = TOKEN_TTL_SECONDS
-            .try_into()
-            .wrap_err("failed to case TOKEN_TTL_SECONDS to u64")
-            .map_err(BannedTokenStoreError::UnexpectedError)?;
+        // The ban only needs to outlive the token itself
+        let ttl = expires_at.saturating_sub(now_timestamp()).max(1);
 
         let _: () = self
             .conn
@@ -44,8 +45,8 @@ impl BannedTokenStore for RedisBannedTokenStore {
         Ok(())
     }
     #[tracing::instrument(name = "Checking for banned token in Redis", skip_all)]
-    async fn contains_token(&self, token: Secret<String>) -> Result<bool, BannedTokenStoreError> {
-        let token_key = get_key(&token.expose_secret());
+    async fn contains_token(&self, jti: &str) -> Result<bool, BannedTokenStoreError> {
+        let token_key = get_key(jti);
 
         let is_banned = self
             .conn
@@ -57,10 +58,55 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
         Ok(is_banned)
     }
+    #[tracing::instrument(name = "Revoking all tokens in Redis", skip_all)]
+    async fn revoke_all_tokens(
+        &mut self,
+        subject: &str,
+        revoked_at: u64,
+    ) -> Result<(), BannedTokenStoreError> {
+        let epoch_key = get_epoch_key(subject);
+
+        let mut conn = self.conn.write().await;
+
+        let current: Option<u64> = conn
+            .get(&epoch_key)
+            .wrap_err("failed to get revocation epoch from Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        if current.is_some_and(|current| current >= revoked_at) {
+            return Ok(());
+        }
+
+        let _: () = conn
+            .set(&epoch_key, revoked_at)
+            .wrap_err("failed to set revocation epoch in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Getting revocation epoch from Redis", skip_all)]
+    async fn get_revocation_epoch(
+        &self,
+        subject: &str,
+    ) -> Result<Option<u64>, BannedTokenStoreError> {
+        let epoch_key = get_epoch_key(subject);
+
+        self.conn
+            .write()
+            .await
+            .get(&epoch_key)
+            .wrap_err("failed to get revocation epoch from Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)
+    }
 }
 
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token";
+const REVOCATION_EPOCH_KEY_PREFIX: &str = "revocation_epoch";
+
+fn get_key(jti: &str) -> String {
+    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, jti)
+}
 
-fn get_key(token: &str) -> String {
-    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
+fn get_epoch_key(subject: &str) -> String {
+    format!("{}{}", REVOCATION_EPOCH_KEY_PREFIX, subject)
 }
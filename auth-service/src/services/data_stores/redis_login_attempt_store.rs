@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptStore, LoginAttemptStoreError, LoginAttempts},
+        Email,
+    },
+    utils::auth::now_timestamp,
+};
+
+pub struct RedisLoginAttemptStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisLoginAttemptStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptStore for RedisLoginAttemptStore {
+    #[tracing::instrument(name = "Retrieving login attempts from Redis", skip_all)]
+    async fn get_attempts(&self, email: &Email) -> Result<LoginAttempts, LoginAttemptStoreError> {
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get(get_key(email))
+            .wrap_err("failed to get login attempts from Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+
+        match value {
+            Some(value) => deserialize_attempts(&value),
+            None => Ok(LoginAttempts::default()),
+        }
+    }
+    #[tracing::instrument(name = "Setting login attempts in Redis", skip_all)]
+    async fn set_attempts(
+        &mut self,
+        email: &Email,
+        attempts: LoginAttempts,
+    ) -> Result<(), LoginAttemptStoreError> {
+        // Redis forgets the failures at the same time they stop counting
+        let ttl = attempts.expires_at().saturating_sub(now_timestamp()).max(1);
+
+        let serialized_attempts = serialize_attempts(&attempts)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_key(email), serialized_attempts, ttl)
+            .wrap_err("failed to set login attempts in Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Removing login attempts from Redis", skip_all)]
+    async fn remove_attempts(&mut self, email: &Email) -> Result<(), LoginAttemptStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(get_key(email))
+            .wrap_err("failed to delete login attempts from Redis")
+            .map_err(LoginAttemptStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct StoredLoginAttempts {
+    failures: u32,
+    last_failed_at: u64,
+    locked_until: Option<u64>,
+}
+
+fn serialize_attempts(attempts: &LoginAttempts) -> Result<String, LoginAttemptStoreError> {
+    let data = StoredLoginAttempts {
+        failures: attempts.failures,
+        last_failed_at: attempts.last_failed_at,
+        locked_until: attempts.locked_until,
+    };
+
+    serde_json::to_string(&data)
+        .wrap_err("failed to serialize login attempts")
+        .map_err(LoginAttemptStoreError::UnexpectedError)
+}
+
+fn deserialize_attempts(value: &str) -> Result<LoginAttempts, LoginAttemptStoreError> {
+    let data: StoredLoginAttempts = serde_json::from_str(value)
+        .wrap_err("failed to deserialize login attempts")
+        .map_err(LoginAttemptStoreError::UnexpectedError)?;
+
+    Ok(LoginAttempts {
+        failures: data.failures,
+        last_failed_at: data.last_failed_at,
+        locked_until: data.locked_until,
+    })
+}
+
+const LOGIN_ATTEMPTS_PREFIX: &str = "login_attempts";
+
+fn get_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        LOGIN_ATTEMPTS_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection, SetExpiry, SetOptions};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{
+            RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError,
+        },
+        Email,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    #[tracing::instrument(name = "Adding refresh token to Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let token_key = get_token_key(token.as_ref().expose_secret());
+        let family_key = get_family_key(&record.family_id);
+
+        let serialized_record = serialize_record(&record)?;
+
+        let ttl: u64 = REFRESH_TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("failed to cast REFRESH_TOKEN_TTL_SECONDS to u64")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .set_ex(&token_key, serialized_record, ttl)
+            .wrap_err("failed to set refresh token in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        // The family outlives each of its tokens by at most one rotation, so
+        // refreshing its expiry on every insert keeps it around as long as needed.
+        let _: () = conn
+            .sadd(&family_key, &token_key)
+            .wrap_err("failed to add refresh token to its family in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let _: () = conn
+            .expire(&family_key, REFRESH_TOKEN_TTL_SECONDS)
+            .wrap_err("failed to set refresh token family expiry in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving refresh token from Redis", skip_all)]
+    async fn get_token(
+        &self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let token_key = get_token_key(token.as_ref().expose_secret());
+
+        match self.conn.write().await.get::<_, String>(&token_key) {
+            Ok(value) => deserialize_record(&value),
+            Err(_) => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    #[tracing::instrument(name = "Marking refresh token as used in Redis", skip_all)]
+    async fn mark_token_used(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let mut record = self.get_token(token).await?;
+        record.used = true;
+
+        let token_key = get_token_key(token.as_ref().expose_secret());
+        let serialized_record = serialize_record(&record)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_options(
+                &token_key,
+                serialized_record,
+                SetOptions::default().with_expiration(SetExpiry::KEEPTTL),
+            )
+            .wrap_err("failed to mark refresh token as used in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking refresh token family in Redis", skip_all)]
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        let family_key = get_family_key(family_id);
+
+        let mut conn = self.conn.write().await;
+
+        let token_keys: Vec<String> = conn
+            .smembers(&family_key)
+            .wrap_err("failed to read refresh token family from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        if !token_keys.is_empty() {
+            let _: () = conn
+                .del(&token_keys)
+                .wrap_err("failed to delete refresh tokens from Redis")
+                .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        }
+
+        let _: () = conn
+            .del(&family_key)
+            .wrap_err("failed to delete refresh token family from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct StoredRefreshToken {
+    email: String,
+    audience: String,
+    family_id: String,
+    authenticated_at: u64,
+    used: bool,
+}
+
+fn serialize_record(record: &RefreshTokenRecord) -> Result<String, RefreshTokenStoreError> {
+    let data = StoredRefreshToken {
+        email: record.email.as_ref().expose_secret().to_owned(),
+        audience: record.audience.clone(),
+        family_id: record.family_id.clone(),
+        authenticated_at: record.authenticated_at,
+        used: record.used,
+    };
+
+    serde_json::to_string(&data)
+        .wrap_err("failed to serialize refresh token record")
+        .map_err(RefreshTokenStoreError::UnexpectedError)
+}
+
+fn deserialize_record(value: &str) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+    let data: StoredRefreshToken = serde_json::from_str(value)
+        .wrap_err("failed to deserialize refresh token record")
+        .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+    let email =
+        Email::parse(Secret::new(data.email)).map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+    Ok(RefreshTokenRecord {
+        email,
+        audience: data.audience,
+        family_id: data.family_id,
+        authenticated_at: data.authenticated_at,
+        used: data.used,
+    })
+}
+
+const REFRESH_TOKEN_KEY_PREFIX: &str = "refresh_token";
+const REFRESH_TOKEN_FAMILY_KEY_PREFIX: &str = "refresh_token_family";
+
+fn get_token_key(token: &str) -> String {
+    format!("{}{}", REFRESH_TOKEN_KEY_PREFIX, token)
+}
+
+fn get_family_key(family_id: &str) -> String {
+    format!("{}{}", REFRESH_TOKEN_FAMILY_KEY_PREFIX, family_id)
+}
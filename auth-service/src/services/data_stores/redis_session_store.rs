@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{Session, SessionStore, SessionStoreError},
+        Email,
+    },
+    utils::auth::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct RedisSessionStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisSessionStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    #[tracing::instrument(name = "Adding session to Redis", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        let session_key = get_session_key(&session.id);
+        let user_key = get_user_key(session.email.as_ref().expose_secret());
+
+        let serialized_session = serialize_session(&session)?;
+        let ttl = session_ttl()?;
+
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .set_ex(&session_key, serialized_session, ttl)
+            .wrap_err("failed to set session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        let _: () = conn
+            .sadd(&user_key, &session.id)
+            .wrap_err("failed to add session to user's sessions in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        let _: () = conn
+            .expire(&user_key, ttl as i64)
+            .wrap_err("failed to set expiry on user's sessions in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Retrieving session from Redis", skip_all)]
+    async fn get_session(&self, id: &str) -> Result<Session, SessionStoreError> {
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get(get_session_key(id))
+            .wrap_err("failed to get session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        match value {
+            Some(value) => deserialize_session(&value),
+            None => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+    #[tracing::instrument(name = "Retrieving user's sessions from Redis", skip_all)]
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let user_key = get_user_key(email.as_ref().expose_secret());
+
+        let mut conn = self.conn.write().await;
+
+        let ids: Vec<String> = conn
+            .smembers(&user_key)
+            .wrap_err("failed to get user's sessions from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut sessions = Vec::with_capacity(ids.len());
+        for id in ids {
+            let value: Option<String> = conn
+                .get(get_session_key(&id))
+                .wrap_err("failed to get session from Redis")
+                .map_err(SessionStoreError::UnexpectedError)?;
+
+            match value {
+                Some(value) => sessions.push(deserialize_session(&value)?),
+                // The session expired on its own, so forget about it
+                None => {
+                    let _: () = conn
+                        .srem(&user_key, &id)
+                        .wrap_err("failed to remove expired session from Redis")
+                        .map_err(SessionStoreError::UnexpectedError)?;
+                }
+            }
+        }
+
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+    #[tracing::instrument(name = "Updating session in Redis", skip_all)]
+    async fn touch_session(
+        &mut self,
+        id: &str,
+        last_seen_at: u64,
+    ) -> Result<(), SessionStoreError> {
+        let mut session = self.get_session(id).await?;
+        session.last_seen_at = last_seen_at;
+
+        let serialized_session = serialize_session(&session)?;
+        let ttl = session_ttl()?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_session_key(id), serialized_session, ttl)
+            .wrap_err("failed to update session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Deleting session from Redis", skip_all)]
+    async fn delete_session(&mut self, id: &str) -> Result<(), SessionStoreError> {
+        let session = self.get_session(id).await?;
+        let user_key = get_user_key(session.email.as_ref().expose_secret());
+
+        let mut conn = self.conn.write().await;
+
+        let _: () = conn
+            .del(get_session_key(id))
+            .wrap_err("failed to delete session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        let _: () = conn
+            .srem(&user_key, id)
+            .wrap_err("failed to remove session from user's sessions in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct StoredSession {
+    id: String,
+    email: String,
+    device: Option<String>,
+    ip: Option<String>,
+    user_agent: Option<String>,
+    created_at: u64,
+    last_seen_at: u64,
+}
+
+fn serialize_session(session: &Session) -> Result<String, SessionStoreError> {
+    let data = StoredSession {
+        id: session.id.clone(),
+        email: session.email.as_ref().expose_secret().to_owned(),
+        device: session.device.clone(),
+        ip: session.ip.clone(),
+        user_agent: session.user_agent.clone(),
+        created_at: session.created_at,
+        last_seen_at: session.last_seen_at,
+    };
+
+    serde_json::to_string(&data)
+        .wrap_err("failed to serialize session")
+        .map_err(SessionStoreError::UnexpectedError)
+}
+
+fn deserialize_session(value: &str) -> Result<Session, SessionStoreError> {
+    let data: StoredSession = serde_json::from_str(value)
+        .wrap_err("failed to deserialize session")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+    let email =
+        Email::parse(Secret::new(data.email)).map_err(SessionStoreError::UnexpectedError)?;
+
+    Ok(Session {
+        id: data.id,
+        email,
+        device: data.device,
+        ip: data.ip,
+        user_agent: data.user_agent,
+        created_at: data.created_at,
+        last_seen_at: data.last_seen_at,
+    })
+}
+
+// A session lives as long as the refresh tokens that keep it going
+fn session_ttl() -> Result<u64, SessionStoreError> {
+    REFRESH_TOKEN_TTL_SECONDS
+        .try_into()
+        .wrap_err("failed to cast REFRESH_TOKEN_TTL_SECONDS to u64")
+        .map_err(SessionStoreError::UnexpectedError)
+}
+
+const SESSION_KEY_PREFIX: &str = "session";
+const USER_SESSIONS_KEY_PREFIX: &str = "user_sessions";
+
+fn get_session_key(id: &str) -> String {
+    format!("{}{}", SESSION_KEY_PREFIX, id)
+}
+
+fn get_user_key(email: &str) -> String {
+    format!("{}{}", USER_SESSIONS_KEY_PREFIX, email)
+}
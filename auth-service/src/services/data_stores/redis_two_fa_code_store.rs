@@ -6,9 +6,12 @@ use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    Email,
+use crate::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+        Email,
+    },
+    utils::auth::now_timestamp,
 };
 
 pub struct RedisTwoFACodeStore {
@@ -29,73 +32,290 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         email: Email,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
+        audience: String,
     ) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(&email);
+        let data = StoredCode {
+            email: email.as_ref().expose_secret().to_owned(),
+            code: code.as_ref().expose_secret().to_owned(),
+            audience,
+            sent_at: now_timestamp(),
+            resends: 0,
+        };
 
-        let data = TwoFATuple(
-            login_attempt_id.as_ref().expose_secret().to_owned(),
-            code.as_ref().expose_secret().to_owned(),
-        );
+        self.set_code(&login_attempt_id, &data).await
+    }
+    #[tracing::instrument(name = "Removing 2FA code from Redis", skip_all)]
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let data = self.get_stored_code(login_attempt_id).await?;
+        let user_key = get_user_key(&data.email);
 
-        let serialized_data = serde_json::to_string(&data)
-            .wrap_err("failed to serialize 2FA tuple")
-            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let mut conn = self.conn.write().await;
 
-        let _: () = self
-            .conn
-            .write()
-            .await
-            .set_ex(&key, serialized_data, TEN_MINUTES_IN_SECONDS)
-            .wrap_err("failed to set 2FA code in Redis")
+        let _: () = conn
+            .del(&[
+                get_key(login_attempt_id),
+                get_failures_key(login_attempt_id),
+            ])
+            .wrap_err("failed to delete 2FA code from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let _: () = conn
+            .srem(&user_key, login_attempt_id.as_ref().expose_secret())
+            .wrap_err("failed to remove 2FA code from user's codes in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
         Ok(())
     }
-    #[tracing::instrument(name = "Removing 2FA code from Redis", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(email);
+    #[tracing::instrument(name = "Removing user's 2FA codes from Redis", skip_all)]
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let user_key = get_user_key(email.as_ref().expose_secret());
 
-        let _: () = self
-            .conn
-            .write()
-            .await
-            .del(&key)
-            .wrap_err("failed to delete 2FA code from Redis")
+        let mut conn = self.conn.write().await;
+
+        let ids: Vec<String> = conn
+            .smembers(&user_key)
+            .wrap_err("failed to get user's 2FA codes from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let mut keys = vec![user_key];
+        for id in ids {
+            keys.push(format!("{}{}", TWO_FA_CODE_PREFIX, id));
+            keys.push(format!("{}{}", TWO_FA_FAILURES_PREFIX, id));
+        }
+
+        let _: () = conn
+            .del(&keys)
+            .wrap_err("failed to delete user's 2FA codes from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
         Ok(())
     }
     #[tracing::instrument(name = "Retrieving 2FA code from Redis", skip_all)]
     async fn get_code(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(Email, TwoFACode), TwoFACodeStoreError> {
+        let data = self.get_stored_code(login_attempt_id).await?;
+
+        let email =
+            Email::parse(Secret::new(data.email)).map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let two_fa_code = TwoFACode::parse(Secret::new(data.code))
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok((email, two_fa_code))
+    }
+    #[tracing::instrument(name = "Retrieving user's 2FA codes from Redis", skip_all)]
+    async fn get_codes(
         &self,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        let key = get_key(email);
+    ) -> Result<Vec<(LoginAttemptId, TwoFACode)>, TwoFACodeStoreError> {
+        let user_key = get_user_key(email.as_ref().expose_secret());
+
+        let mut conn = self.conn.write().await;
 
-        match self.conn.write().await.get::<_, String>(&key) {
-            Ok(value) => {
-                let data: TwoFATuple = serde_json::from_str(&value)
-                    .wrap_err("failed to deserialize 2FA tuple")
-                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let ids: Vec<String> = conn
+            .smembers(&user_key)
+            .wrap_err("failed to get user's 2FA codes from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-                let login_attempt_id = LoginAttemptId::parse(Secret::new(data.0))
-                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
-                let two_fa_code = TwoFACode::parse(Secret::new(data.1))
-                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let mut codes = Vec::with_capacity(ids.len());
+        for id in ids {
+            let value: Option<String> = conn
+                .get(format!("{}{}", TWO_FA_CODE_PREFIX, id))
+                .wrap_err("failed to get 2FA code from Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-                Ok((login_attempt_id, two_fa_code))
+            match value {
+                Some(value) => {
+                    let data = deserialize_code(&value)?;
+
+                    let login_attempt_id = LoginAttemptId::parse(Secret::new(id))
+                        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+                    let two_fa_code = TwoFACode::parse(Secret::new(data.code))
+                        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+                    codes.push((login_attempt_id, two_fa_code));
+                }
+                // The code expired on its own, so forget about it
+                None => {
+                    let _: () = conn
+                        .srem(&user_key, &id)
+                        .wrap_err("failed to remove expired 2FA code from Redis")
+                        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+                }
             }
-            Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
+
+        Ok(codes)
+    }
+    #[tracing::instrument(name = "Retrieving 2FA login audience from Redis", skip_all)]
+    async fn get_audience(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<String, TwoFACodeStoreError> {
+        Ok(self.get_stored_code(login_attempt_id).await?.audience)
+    }
+    // Failures are counted under their own key with INCR, so concurrent
+    // requests cannot both read the same count and get an extra guess
+    #[tracing::instrument(name = "Recording failed 2FA attempt in Redis", skip_all)]
+    async fn record_failed_attempt(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        self.get_stored_code(login_attempt_id).await?;
+
+        let failures_key = get_failures_key(login_attempt_id);
+
+        let failures: u32 = {
+            let mut conn = self.conn.write().await;
+
+            let failures = conn
+                .incr(&failures_key, 1)
+                .wrap_err("failed to count failed 2FA attempt in Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+            let _: () = conn
+                .expire(&failures_key, TEN_MINUTES_IN_SECONDS as i64)
+                .wrap_err("failed to set expiry on failed 2FA attempts in Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+            failures
+        };
+
+        if failures >= max_attempts {
+            self.remove_code(login_attempt_id).await?;
+
+            return Err(TwoFACodeStoreError::TooManyFailedAttempts);
+        }
+
+        Ok(())
+    }
+    #[tracing::instrument(name = "Recording 2FA code resend in Redis", skip_all)]
+    async fn record_resend(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        cooldown_seconds: u64,
+        max_resends: u32,
+    ) -> Result<TwoFACode, TwoFACodeStoreError> {
+        let mut data = self.get_stored_code(login_attempt_id).await?;
+
+        if data.resends >= max_resends {
+            return Err(TwoFACodeStoreError::TooManyResends);
+        }
+
+        let now = now_timestamp();
+
+        if now < data.sent_at + cooldown_seconds {
+            return Err(TwoFACodeStoreError::ResendTooSoon);
+        }
+
+        data.sent_at = now;
+        data.resends += 1;
+
+        // The user has just been told about the code, so it gets a full
+        // lifetime again, along with its failure count and the user's index
+        self.set_code(login_attempt_id, &data).await?;
+
+        TwoFACode::parse(Secret::new(data.code)).map_err(TwoFACodeStoreError::UnexpectedError)
+    }
+}
+
+impl RedisTwoFACodeStore {
+    async fn get_stored_code(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<StoredCode, TwoFACodeStoreError> {
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get(get_key(login_attempt_id))
+            .wrap_err("failed to get 2FA code from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        match value {
+            Some(value) => deserialize_code(&value),
+            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        }
+    }
+
+    // The code, its failure count and the user's index of codes always get
+    // the same expiry in one transaction. Otherwise the failure count could
+    // reset while the code is still live, or the index could forget a code.
+    async fn set_code(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+        data: &StoredCode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let serialized_data = serde_json::to_string(data)
+            .wrap_err("failed to serialize 2FA code")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let user_key = get_user_key(&data.email);
+        let ttl = TEN_MINUTES_IN_SECONDS as i64;
+
+        let _: () = redis::pipe()
+            .atomic()
+            .set_ex(
+                get_key(login_attempt_id),
+                serialized_data,
+                TEN_MINUTES_IN_SECONDS,
+            )
+            .ignore()
+            .sadd(&user_key, login_attempt_id.as_ref().expose_secret())
+            .ignore()
+            .expire(&user_key, ttl)
+            .ignore()
+            .expire(get_failures_key(login_attempt_id), ttl)
+            .ignore()
+            .query(&mut *self.conn.write().await)
+            .wrap_err("failed to set 2FA code in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
     }
 }
 
 #[derive(Deserialize, Serialize)]
-struct TwoFATuple(pub String, pub String);
+struct StoredCode {
+    email: String,
+    code: String,
+    audience: String,
+    sent_at: u64,
+    resends: u32,
+}
+
+fn deserialize_code(value: &str) -> Result<StoredCode, TwoFACodeStoreError> {
+    serde_json::from_str(value)
+        .wrap_err("failed to deserialize 2FA code")
+        .map_err(TwoFACodeStoreError::UnexpectedError)
+}
 
 const TEN_MINUTES_IN_SECONDS: u64 = 600;
-const TWO_FA_CODE_PREFIX: &str = "two_fa_code";
+const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+const TWO_FA_FAILURES_PREFIX: &str = "two_fa_failures:";
+const USER_TWO_FA_CODES_PREFIX: &str = "user_two_fa_codes:";
+
+fn get_key(login_attempt_id: &LoginAttemptId) -> String {
+    format!(
+        "{}{}",
+        TWO_FA_CODE_PREFIX,
+        login_attempt_id.as_ref().expose_secret()
+    )
+}
+
+// Keyed by login attempt, so a new login starts counting from zero
+fn get_failures_key(login_attempt_id: &LoginAttemptId) -> String {
+    format!(
+        "{}{}",
+        TWO_FA_FAILURES_PREFIX,
+        login_attempt_id.as_ref().expose_secret()
+    )
+}
 
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+fn get_user_key(email: &str) -> String {
+    format!("{}{}", USER_TWO_FA_CODES_PREFIX, email)
 }
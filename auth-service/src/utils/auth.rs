@@ -1,41 +1,237 @@
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
 use chrono::Utc;
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{decode, decode_header, encode};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::{app_state::BannedTokenStoreType, domain::email::Email};
+use crate::{
+    app_state::{
+        AppState, BannedTokenStoreType, RefreshTokenStoreType, SessionStoreType, UserStoreType,
+    },
+    domain::{
+        data_stores::{
+            RefreshToken, RefreshTokenRecord, Session, SessionStoreError, UserStore, UserStoreError,
+        },
+        email::Email,
+        user::UserStatus,
+        RecoveryCode,
+    },
+};
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::{
+    constants::{
+        COOKIE_CONFIG, JWT_AUDIENCES, JWT_COOKIE_NAME, JWT_ISSUER, JWT_LEEWAY_SECONDS,
+        REFRESH_TOKEN_COOKIE_NAME,
+    },
+    keys::KEY_RING,
+};
 
 #[tracing::instrument(name = "Auth generating cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(
+    user_id: Uuid,
+    email: &Email,
+    audience: &str,
+    session_id: &str,
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(user_id, email, audience, session_id)?;
     Ok(create_auth_cookie(token))
 }
 
+// Records a new session and issues the auth and refresh cookies that belong to it
+#[tracing::instrument(name = "Auth starting session", skip_all)]
+pub async fn start_session(
+    session: Session,
+    user_id: Uuid,
+    audience: String,
+    session_store: SessionStoreType,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<(Cookie<'static>, Cookie<'static>)> {
+    let auth_cookie = generate_auth_cookie(user_id, &session.email, &audience, &session.id)?;
+
+    let record = RefreshTokenRecord::new(
+        session.email.clone(),
+        audience,
+        session.id.clone(),
+        session.created_at,
+    );
+
+    session_store
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .wrap_err("failed to store session")?;
+
+    let refresh_cookie = generate_refresh_cookie(record, refresh_token_store).await?;
+
+    Ok((auth_cookie, refresh_cookie))
+}
+
+// Resolves the audience a client asked for, falling back to the default one.
+// Tokens can only be minted for audiences we know about.
+pub fn parse_audience(requested: Option<String>) -> Result<String> {
+    match requested {
+        None => Ok(JWT_AUDIENCES[0].clone()),
+        Some(audience) if JWT_AUDIENCES.contains(&audience) => Ok(audience),
+        Some(audience) => Err(eyre!("{} is not a known audience", audience)),
+    }
+}
+
 #[tracing::instrument(name = "Auth creating cookie", skip_all)]
 fn create_auth_cookie(token: String) -> Cookie<'static> {
-    let cookie = Cookie::build((JWT_COOKIE_NAME, token))
-        .path("/") // apple cookie to all URLs on the server
-        .http_only(true) // prevent JavaScript from accessing the cookie
-        .same_site(SameSite::Lax) // send cookie with "same-site" requests, and with "cross-site" top-level navigations.
-        .build();
+    COOKIE_CONFIG.build(
+        JWT_COOKIE_NAME,
+        token,
+        time::Duration::seconds(TOKEN_TTL_SECONDS),
+    )
+}
+
+// Forgets a session and revokes its refresh token family. Access tokens issued
+// for it stop validating on their next use.
+#[tracing::instrument(name = "Auth ending session", skip_all)]
+pub async fn end_session(state: &AppState, session_id: &str) -> Result<()> {
+    match state
+        .session_store
+        .write()
+        .await
+        .delete_session(session_id)
+        .await
+    {
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => (),
+        Err(e) => return Err(e).wrap_err("failed to delete session"),
+    }
+
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_family(session_id)
+        .await
+        .wrap_err("failed to revoke refresh token family")?;
+
+    Ok(())
+}
+
+// Revokes every token issued to the user so far and ends all of their sessions
+#[tracing::instrument(name = "Auth ending all sessions", skip_all)]
+pub async fn end_all_sessions(state: &AppState, email: &Email) -> Result<()> {
+    state
+        .banned_token_store
+        .write()
+        .await
+        .revoke_all_tokens(email.as_ref().expose_secret(), now_timestamp())
+        .await
+        .wrap_err("failed to revoke user's tokens")?;
 
-    cookie
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(email)
+        .await
+        .wrap_err("failed to get user's sessions")?;
+
+    for session in sessions {
+        end_session(state, &session.id).await?;
+    }
+
+    Ok(())
+}
+
+// Returns the time a new login for the user starts at. Tokens issued in the
+// same second the user's tokens were revoked count as revoked too, so a login
+// in that second waits for the next one.
+#[tracing::instrument(name = "Auth getting login time", skip_all)]
+pub async fn login_timestamp(state: &AppState, email: &Email) -> Result<u64> {
+    let revoked_at = state
+        .banned_token_store
+        .read()
+        .await
+        .get_revocation_epoch(email.as_ref().expose_secret())
+        .await
+        .wrap_err("failed to get user's revocation epoch")?;
+
+    if let Some(revoked_at) = revoked_at {
+        let now_millis = u64::try_from(Utc::now().timestamp_millis()).unwrap_or_default();
+        let wait_millis = ((revoked_at + 1) * 1000).saturating_sub(now_millis);
+
+        tokio::time::sleep(std::time::Duration::from_millis(wait_millis.min(1000))).await;
+    }
+
+    Ok(now_timestamp())
+}
+
+// Replaces the user's recovery codes with a new set. Only hashes are stored,
+// so the returned codes must be shown to the user now or never.
+#[tracing::instrument(name = "Auth issuing recovery codes", skip_all)]
+pub async fn issue_recovery_codes(
+    user_store: &mut (dyn UserStore + Send + Sync),
+    user_id: Uuid,
+) -> Result<Vec<Secret<String>>> {
+    let codes = RecoveryCode::generate_set();
+
+    user_store
+        .replace_recovery_codes(user_id, codes.iter().map(RecoveryCode::hash).collect())
+        .await
+        .wrap_err("failed to store recovery codes")?;
+
+    Ok(codes.iter().map(RecoveryCode::formatted).collect())
+}
+
+// Expires the auth and refresh cookies with the same attributes they were set with
+pub fn remove_auth_cookies(jar: CookieJar) -> CookieJar {
+    jar.remove(COOKIE_CONFIG.removal(JWT_COOKIE_NAME))
+        .remove(COOKIE_CONFIG.removal(REFRESH_TOKEN_COOKIE_NAME))
 }
 
 // This value determines how long the JWT auth token is valid for
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
 
+// This value determines how long an unused refresh token can be exchanged for a new JWT
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24 * 14; // 14 days
+
+#[tracing::instrument(name = "Auth generating refresh cookie", skip_all)]
+pub async fn generate_refresh_cookie(
+    record: RefreshTokenRecord,
+    refresh_token_store: RefreshTokenStoreType,
+) -> Result<Cookie<'static>> {
+    let token = RefreshToken::default();
+
+    refresh_token_store
+        .write()
+        .await
+        .add_token(token.clone(), record)
+        .await
+        .wrap_err("failed to store refresh token")?;
+
+    Ok(create_refresh_cookie(token))
+}
+
+#[tracing::instrument(name = "Auth creating refresh cookie", skip_all)]
+fn create_refresh_cookie(token: RefreshToken) -> Cookie<'static> {
+    COOKIE_CONFIG.build(
+        REFRESH_TOKEN_COOKIE_NAME,
+        token.as_ref().expose_secret().to_owned(),
+        time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS), // outlive the browser session
+    )
+}
+
 #[tracing::instrument(name = "Auth generating token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
+fn generate_auth_token(
+    user_id: Uuid,
+    email: &Email,
+    audience: &str,
+    session_id: &str,
+) -> Result<String> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .wrap_err("failed to create 10 minute time delta")?;
 
+    let now = Utc::now();
+
     // Create JWT expiration time
-    let exp = Utc::now()
+    let exp = now
         .checked_add_signed(delta)
         .ok_or(eyre!("failed to add 10 minutes to current time"))?
         .timestamp();
@@ -46,63 +242,187 @@ fn generate_auth_token(email: &Email) -> Result<String> {
         exp
     ))?;
 
-    let sub = email.as_ref().expose_secret().to_owned();
+    let iat: usize = now.timestamp().try_into().wrap_err(format!(
+        "failed to cast iat time to usize. iat time: {}",
+        now.timestamp()
+    ))?;
 
-    let claims = Claims { sub, exp };
+    let claims = Claims {
+        sub: user_id.to_string(),
+        email: email.as_ref().expose_secret().to_owned(),
+        exp,
+        iat,
+        nbf: iat,
+        jti: uuid::Uuid::new_v4().to_string(),
+        iss: JWT_ISSUER.to_owned(),
+        aud: audience.to_owned(),
+        sid: session_id.to_owned(),
+    };
 
     create_token(&claims)
 }
 
-#[tracing::instrument(name = "Auth validating token", skip_all)]
+// Accepts tokens minted for any of our audiences
 pub async fn validate_token(
     token: &Secret<String>,
     banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
+    user_store: UserStoreType,
 ) -> Result<Claims> {
-    let banned_token_store_lock = banned_token_store.read().await;
+    validate_token_for_audiences(
+        token,
+        &JWT_AUDIENCES,
+        banned_token_store,
+        session_store,
+        user_store,
+    )
+    .await
+}
+
+#[tracing::instrument(name = "Auth validating token", skip_all)]
+pub async fn validate_token_for_audiences<T: ToString>(
+    token: &Secret<String>,
+    audiences: &[T],
+    banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
+    user_store: UserStoreType,
+) -> Result<Claims> {
+    let claims = check_token(token, audiences, banned_token_store, user_store).await?;
 
-    match banned_token_store_lock
-        .contains_token(token.to_owned())
+    // Ending a session revokes every token issued for it. Otherwise the use
+    // of a token counts as activity on its session.
+    match session_store
+        .write()
+        .await
+        .touch_session(&claims.sid, now_timestamp())
         .await
     {
-        Ok(true) => return Err(eyre!("token is banned")),
-        Ok(false) => (),
+        Ok(()) => Ok(claims),
+        Err(SessionStoreError::SessionNotFound) => Err(eyre!("token's session has ended")),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Like `validate_token`, for services asking about a token on the user's
+// behalf. Their asking is not activity by the user, so the session is only
+// checked and not kept alive.
+#[tracing::instrument(name = "Auth inspecting token", skip_all)]
+pub async fn inspect_token(
+    token: &Secret<String>,
+    banned_token_store: BannedTokenStoreType,
+    session_store: SessionStoreType,
+    user_store: UserStoreType,
+) -> Result<Claims> {
+    let claims = check_token(token, &JWT_AUDIENCES, banned_token_store, user_store).await?;
+
+    match session_store.read().await.get_session(&claims.sid).await {
+        Ok(_) => Ok(claims),
+        Err(SessionStoreError::SessionNotFound) => Err(eyre!("token's session has ended")),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Everything about a token except its session
+async fn check_token<T: ToString>(
+    token: &Secret<String>,
+    audiences: &[T],
+    banned_token_store: BannedTokenStoreType,
+    user_store: UserStoreType,
+) -> Result<Claims> {
+    let header = decode_header(token.expose_secret()).wrap_err("failed to decode token header")?;
+    let key = KEY_RING
+        .verification_key(header.kid.as_deref())
+        .ok_or(eyre!("token was not signed by a known key"))?;
+
+    let mut validation = key.validation();
+    validation.leeway = *JWT_LEEWAY_SECONDS;
+    validation.validate_nbf = true;
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_audience(audiences);
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+
+    let claims = decode::<Claims>(token.expose_secret(), key.decoding_key(), &validation)
+        .map(|data| data.claims)
+        .wrap_err("failed to decode token")?;
+
+    let banned_token_store_lock = banned_token_store.read().await;
+
+    if banned_token_store_lock.contains_token(&claims.jti).await? {
+        return Err(eyre!("token is banned"));
+    }
+
+    // Logging out everywhere revokes every token issued up to that moment,
+    // including the rest of its second, since `iat` is in whole seconds
+    if let Some(revoked_at) = banned_token_store_lock
+        .get_revocation_epoch(&claims.email)
+        .await?
+    {
+        if (claims.iat as u64) <= revoked_at {
+            return Err(eyre!(
+                "token was issued before the user's tokens were revoked"
+            ));
+        }
+    }
+
+    // Suspending or disabling a user ends their sessions, but a token must
+    // not outlive that if ending them failed part way
+    let email = Email::parse(Secret::new(claims.email.clone()))?;
+
+    match user_store.read().await.get_user(&email).await {
+        Ok(user) if user.status == UserStatus::Active => (),
+        Ok(_) => return Err(eyre!("token's user is not active")),
+        Err(UserStoreError::UserNotFound) => return Err(eyre!("token's user does not exist")),
         Err(e) => return Err(e.into()),
     }
-    decode::<Claims>(
-        token.expose_secret(),
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
-    )
-    .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+
+    Ok(claims)
+}
+
+pub fn now_timestamp() -> u64 {
+    Utc::now().timestamp().try_into().unwrap_or_default()
 }
 
 #[tracing::instrument(name = "Auth creating token", skip_all)]
 fn create_token(claims: &Claims) -> Result<String> {
-    encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-    )
-    .wrap_err("failed to create token")
+    let key = KEY_RING.active_key();
+
+    encode(&key.header(), &claims, key.encoding_key()).wrap_err("failed to create token")
 }
 
+// `sub` is the user's id, which stays the same when their email changes.
+// Sessions and revocations are tracked by email, which the token also carries.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
+    pub email: String,
     pub exp: usize,
+    pub iat: usize,
+    pub nbf: usize,
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    pub sid: String,
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
+    use axum_extra::extract::cookie::SameSite;
     use secrecy::Secret;
     use tokio::sync::RwLock;
 
     use crate::{
-        domain::data_stores::BannedTokenStore,
-        services::hashset_banned_token_store::HashsetBannedTokenStore,
+        domain::{
+            data_stores::{RefreshTokenStore, SessionStore},
+            user::User,
+            Password,
+        },
+        services::{
+            hashmap_refresh_token_store::HashmapRefreshTokenStore,
+            hashmap_session_store::HashmapSessionStore, hashmap_user_store::HashmapUserStore,
+            hashset_banned_token_store::HashsetBannedTokenStore,
+        },
     };
 
     use super::*;
@@ -110,7 +430,7 @@ mod tests {
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(USER_ID, &email, "app-service", SESSION_ID).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -127,24 +447,65 @@ mod tests {
         assert_eq!(cookie.path(), Some("/"));
         assert_eq!(cookie.http_only(), Some(true));
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(TOKEN_TTL_SECONDS))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_refresh_cookie() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let record = RefreshTokenRecord::new(
+            email.clone(),
+            "app-service".to_owned(),
+            "family".to_owned(),
+            now_timestamp(),
+        );
+        let cookie = generate_refresh_cookie(record, refresh_token_store.clone())
+            .await
+            .unwrap();
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        );
+
+        let token = RefreshToken::parse(Secret::new(cookie.value().to_owned())).unwrap();
+        let record = refresh_token_store
+            .read()
+            .await
+            .get_token(&token)
+            .await
+            .unwrap();
+        assert_eq!(record.email, email);
+        assert_eq!(record.audience, "app-service");
+        assert_eq!(record.family_id, "family");
+        assert!(!record.used);
     }
 
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(USER_ID, &email, "app-service", SESSION_ID).unwrap();
         assert_eq!(result.split('.').count(), 3);
+
+        let header = decode_header(&result).unwrap();
+        assert_eq!(header.kid.as_deref(), Some(KEY_RING.active_key().kid()));
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
-        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&Secret::new(token), banned_token_store)
-            .await
-            .unwrap();
-        assert_eq!(result.sub, "test@example.com");
+        let (token, stores) = setup().await;
+        let result = stores.validate(&token).await.unwrap();
+        assert_eq!(result.sub, USER_ID.to_string());
+        assert_eq!(result.email, "test@example.com");
+        assert_eq!(result.iss, *JWT_ISSUER);
+        assert_eq!(result.aud, "app-service");
+        assert!(result.nbf <= result.iat);
 
         let exp = Utc::now()
             .checked_add_signed(chrono::Duration::try_minutes(9).expect("valid duration"))
@@ -156,18 +517,282 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
-        let token = "invalid_token".to_owned();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&Secret::new(token), banned_token_store).await;
+        let (_, stores) = setup().await;
+        let result = stores
+            .validate(&Secret::new("invalid_token".to_owned()))
+            .await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
-        let token = Secret::new("banned_token".to_owned());
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let _ = banned_token_store.write().await.add_token(token.clone());
-        let result = validate_token(&token, banned_token_store).await;
+        let (_, stores) = setup().await;
+        let claims = claims("test@example.com");
+        let token = Secret::new(create_token(&claims).unwrap());
+        stores
+            .banned_token_store
+            .write()
+            .await
+            .add_token(claims.jti.clone(), claims.exp as u64)
+            .await
+            .unwrap();
+        let result = stores.validate(&token).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_issued_in_revocation_second() {
+        let (_, stores) = setup().await;
+        let claims = claims("test@example.com");
+        let token = Secret::new(create_token(&claims).unwrap());
+        stores
+            .banned_token_store
+            .write()
+            .await
+            .revoke_all_tokens("test@example.com", claims.iat as u64)
+            .await
+            .unwrap();
+        let result = stores.validate(&token).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_issued_before_revocation_epoch() {
+        let (new_token, stores) = setup().await;
+        let mut claims = claims("test@example.com");
+        claims.iat -= 120;
+        let old_token = Secret::new(create_token(&claims).unwrap());
+        stores
+            .banned_token_store
+            .write()
+            .await
+            .revoke_all_tokens("test@example.com", now_timestamp() - 60)
+            .await
+            .unwrap();
+        let result = stores.validate(&old_token).await;
+        assert!(result.is_err());
+
+        let result = stores.validate(&new_token).await;
+        assert!(result.is_ok());
+    }
+
+    fn claims(email: &str) -> Claims {
+        let now = Utc::now().timestamp() as usize;
+        Claims {
+            sub: USER_ID.to_string(),
+            email: email.to_owned(),
+            exp: now + 600,
+            iat: now,
+            nbf: now,
+            jti: uuid::Uuid::new_v4().to_string(),
+            iss: JWT_ISSUER.to_owned(),
+            aud: "app-service".to_owned(),
+            sid: SESSION_ID.to_owned(),
+        }
+    }
+
+    const SESSION_ID: &str = "session";
+    const USER_ID: Uuid = Uuid::nil();
+
+    // The stores a token is checked against, holding the test user and the
+    // session the token belongs to
+    struct Stores {
+        banned_token_store: BannedTokenStoreType,
+        session_store: SessionStoreType,
+        user_store: UserStoreType,
+    }
+
+    impl Stores {
+        async fn validate(&self, token: &Secret<String>) -> Result<Claims> {
+            self.validate_for_audiences(token, &JWT_AUDIENCES).await
+        }
+
+        async fn validate_for_audiences<T: ToString>(
+            &self,
+            token: &Secret<String>,
+            audiences: &[T],
+        ) -> Result<Claims> {
+            validate_token_for_audiences(
+                token,
+                audiences,
+                self.banned_token_store.clone(),
+                self.session_store.clone(),
+                self.user_store.clone(),
+            )
+            .await
+        }
+    }
+
+    // Builds the stores and issues a token for the test user's session
+    async fn setup() -> (Secret<String>, Stores) {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+
+        let mut session = Session::new(email.clone(), None, None, None, now_timestamp());
+        session.id = SESSION_ID.to_owned();
+        let mut session_store = HashmapSessionStore::default();
+        session_store.add_session(session).await.unwrap();
+
+        let mut user_store = HashmapUserStore::default();
+        user_store
+            .add_user(
+                User::new(
+                    email.clone(),
+                    Password::parse(Secret::new("password123".to_owned())).unwrap(),
+                    false,
+                ),
+                vec![],
+            )
+            .await
+            .unwrap();
+
+        let token =
+            Secret::new(generate_auth_token(USER_ID, &email, "app-service", SESSION_ID).unwrap());
+
+        let stores = Stores {
+            banned_token_store: Arc::new(RwLock::new(HashsetBannedTokenStore::default())),
+            session_store: Arc::new(RwLock::new(session_store)),
+            user_store: Arc::new(RwLock::new(user_store)),
+        };
+
+        (token, stores)
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_for_inactive_or_missing_user() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+
+        for status in [UserStatus::Suspended, UserStatus::Disabled] {
+            let (token, stores) = setup().await;
+            let mut user_store = stores.user_store.write().await;
+            let mut user = user_store.get_user(&email).await.unwrap();
+            user.status = status;
+            user_store.update_user(user).await.unwrap();
+            drop(user_store);
+
+            let result = stores.validate(&token).await;
+            assert!(result.is_err());
+        }
+
+        let (token, stores) = setup().await;
+        stores
+            .user_store
+            .write()
+            .await
+            .delete_user(&email)
+            .await
+            .unwrap();
+        let result = stores.validate(&token).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_ended_session() {
+        let (token, stores) = setup().await;
+        stores
+            .session_store
+            .write()
+            .await
+            .delete_session(SESSION_ID)
+            .await
+            .unwrap();
+        let result = stores.validate(&token).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_start_session() {
+        let (_, stores) = setup().await;
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let session = Session::new(email.clone(), None, None, None, now_timestamp());
+        let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let (auth_cookie, refresh_cookie) = start_session(
+            session.clone(),
+            USER_ID,
+            "app-service".to_owned(),
+            stores.session_store.clone(),
+            refresh_token_store.clone(),
+        )
+        .await
+        .unwrap();
+
+        let claims = stores
+            .validate(&Secret::new(auth_cookie.value().to_owned()))
+            .await
+            .unwrap();
+        assert_eq!(claims.sid, session.id);
+
+        let token = RefreshToken::parse(Secret::new(refresh_cookie.value().to_owned())).unwrap();
+        let record = refresh_token_store
+            .read()
+            .await
+            .get_token(&token)
+            .await
+            .unwrap();
+        assert_eq!(record.family_id, session.id);
+    }
+
+    #[tokio::test]
+    async fn test_generate_auth_token_uses_unique_jti() {
+        let (first, stores) = setup().await;
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let second =
+            Secret::new(generate_auth_token(USER_ID, &email, "app-service", SESSION_ID).unwrap());
+        let first = stores.validate(&first).await.unwrap();
+        let second = stores.validate(&second).await.unwrap();
+        assert_ne!(first.jti, second.jti);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_for_audiences_rejects_other_audience() {
+        let (token, stores) = setup().await;
+        let result = stores
+            .validate_for_audiences(&token, &["other-service"])
+            .await;
+        assert!(result.is_err());
+
+        let result = stores
+            .validate_for_audiences(&token, &["app-service"])
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_with_wrong_issuer() {
+        let (_, stores) = setup().await;
+        let mut claims = claims("test@example.com");
+        claims.iss = "someone-else".to_owned();
+        let token = Secret::new(create_token(&claims).unwrap());
+        let result = stores.validate(&token).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_not_yet_valid() {
+        let (_, stores) = setup().await;
+        let mut claims = claims("test@example.com");
+        claims.nbf += 3600;
+        let token = Secret::new(create_token(&claims).unwrap());
+        let result = stores.validate(&token).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_allows_clock_skew_within_leeway() {
+        let (_, stores) = setup().await;
+        let mut claims = claims("test@example.com");
+        claims.exp = Utc::now().timestamp() as usize - 10;
+        let token = Secret::new(create_token(&claims).unwrap());
+        let result = stores.validate(&token).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_parse_audience() {
+        assert_eq!(parse_audience(None).unwrap(), JWT_AUDIENCES[0]);
+        assert_eq!(
+            parse_audience(Some("app-service".to_owned())).unwrap(),
+            "app-service"
+        );
+        assert!(parse_audience(Some("unknown".to_owned())).is_err());
+    }
 }
@@ -0,0 +1,107 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts},
+};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email},
+};
+
+use super::{
+    auth::{validate_token, Claims},
+    constants::{COOKIE_CONFIG, JWT_COOKIE_NAME},
+};
+
+// The caller of an authenticated route. The JWT can come from the `jwt` cookie
+// used by the browser, or an `Authorization: Bearer` header used by other clients.
+pub struct AuthenticatedUser {
+    pub token: Secret<String>,
+    pub claims: Claims,
+    pub email: Email,
+}
+
+#[async_trait::async_trait]
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = extract_token(parts).ok_or(AuthAPIError::MissingToken)?;
+
+        let claims = validate_token(
+            &token,
+            state.banned_token_store.clone(),
+            state.session_store.clone(),
+            state.user_store.clone(),
+        )
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        let email = Email::parse(Secret::new(claims.email.clone()))
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        Ok(Self {
+            token,
+            claims,
+            email,
+        })
+    }
+}
+
+fn extract_token(parts: &Parts) -> Option<Secret<String>> {
+    if let Some(header) = parts.headers.get(AUTHORIZATION) {
+        return header
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| Secret::new(token.trim().to_owned()));
+    }
+
+    CookieJar::from_headers(&parts.headers)
+        .get(&COOKIE_CONFIG.name(JWT_COOKIE_NAME))
+        .map(|cookie| Secret::new(cookie.value().to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+    use secrecy::ExposeSecret;
+
+    use super::*;
+
+    fn parts(header: (&str, &str)) -> Parts {
+        Request::builder()
+            .header(header.0, header.1)
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0
+    }
+
+    #[test]
+    fn extract_token_reads_bearer_header() {
+        let token = extract_token(&parts(("Authorization", "Bearer abc123"))).unwrap();
+        assert_eq!(token.expose_secret(), "abc123");
+    }
+
+    #[test]
+    fn extract_token_reads_cookie() {
+        let token = extract_token(&parts(("Cookie", "jwt=abc123"))).unwrap();
+        assert_eq!(token.expose_secret(), "abc123");
+    }
+
+    #[test]
+    fn extract_token_ignores_other_schemes() {
+        assert!(extract_token(&parts(("Authorization", "Basic abc123"))).is_none());
+    }
+
+    #[test]
+    fn extract_token_returns_none_without_credentials() {
+        assert!(extract_token(&parts(("Accept", "application/json"))).is_none());
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use secrecy::{ExposeSecret, Secret};
+use subtle::ConstantTimeEq;
+
+use crate::domain::AuthAPIError;
+
+use super::constants::ADMIN_CLIENTS;
+
+// Checks HTTP Basic credentials against the registered clients, returning the client id
+pub fn authenticate_client(
+    headers: &HeaderMap,
+    clients: &HashMap<String, Secret<String>>,
+) -> Option<String> {
+    let credentials = headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let credentials = String::from_utf8(STANDARD.decode(credentials.trim()).ok()?).ok()?;
+    let (client_id, secret) = credentials.split_once(':')?;
+
+    let expected = clients.get(client_id)?;
+
+    if bool::from(expected.expose_secret().as_bytes().ct_eq(secret.as_bytes())) {
+        Some(client_id.to_owned())
+    } else {
+        None
+    }
+}
+
+// A backend allowed to call the admin routes, authenticated with HTTP Basic
+pub struct AdminClient {
+    pub client_id: String,
+}
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AdminClient {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        authenticate_client(&parts.headers, &ADMIN_CLIENTS)
+            .map(|client_id| Self { client_id })
+            .ok_or(AuthAPIError::InvalidClient)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn clients() -> HashMap<String, Secret<String>> {
+        HashMap::from([("admin".to_owned(), Secret::new("secret".to_owned()))])
+    }
+
+    fn headers(credentials: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Basic {}", STANDARD.encode(credentials))).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn authenticate_client_accepts_registered_client() {
+        let client_id = authenticate_client(&headers("admin:secret"), &clients());
+        assert_eq!(client_id, Some("admin".to_owned()));
+    }
+
+    #[test]
+    fn authenticate_client_rejects_wrong_secret() {
+        assert!(authenticate_client(&headers("admin:wrong"), &clients()).is_none());
+    }
+
+    #[test]
+    fn authenticate_client_rejects_unknown_client() {
+        assert!(authenticate_client(&headers("other:secret"), &clients()).is_none());
+    }
+
+    #[test]
+    fn authenticate_client_rejects_missing_credentials() {
+        assert!(authenticate_client(&HeaderMap::new(), &clients()).is_none());
+    }
+}
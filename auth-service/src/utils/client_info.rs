@@ -0,0 +1,143 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+
+use super::constants::TRUSTED_PROXIES;
+
+// Where a request came from, recorded against the session it starts.
+// Behind a proxy the peer address is the proxy, so `X-Forwarded-For` is
+// used instead, but only when the peer is one of our trusted proxies.
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self {
+            ip: extract_ip(parts, &TRUSTED_PROXIES),
+            user_agent: parts
+                .headers
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned),
+        })
+    }
+}
+
+fn extract_ip(parts: &Parts, trusted_proxies: &[IpAddr]) -> Option<String> {
+    let peer = parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())?;
+
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer.to_string());
+    }
+
+    // Each proxy appends the address it got the request from, so the client is
+    // the rightmost hop that is not one of ours. Anything left of it was
+    // written by the client and could be made up.
+    let mut client = peer;
+
+    let forwarded = parts
+        .headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect::<Vec<_>>();
+
+    for hop in forwarded.into_iter().rev() {
+        match hop.trim().parse() {
+            Ok(ip) => client = ip,
+            Err(_) => break,
+        }
+
+        if !trusted_proxies.contains(&client) {
+            break;
+        }
+    }
+
+    Some(client.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+
+    use super::*;
+
+    const PROXY: [u8; 4] = [10, 0, 0, 1];
+
+    fn parts_from(peer: [u8; 4], forwarded: Option<&str>) -> Parts {
+        let mut builder = Request::builder();
+        if let Some(forwarded) = forwarded {
+            builder = builder.header("X-Forwarded-For", forwarded);
+        }
+
+        let mut parts = builder.body(()).unwrap().into_parts().0;
+        parts
+            .extensions
+            .insert(ConnectInfo(SocketAddr::from((peer, 3000))));
+
+        parts
+    }
+
+    #[test]
+    fn extract_ip_uses_forwarded_header_from_trusted_proxy() {
+        let parts = parts_from(PROXY, Some("198.51.100.1, 203.0.113.7"));
+
+        assert_eq!(
+            extract_ip(&parts, &[IpAddr::from(PROXY)]),
+            Some("203.0.113.7".to_owned())
+        );
+    }
+
+    #[test]
+    fn extract_ip_skips_trusted_hops() {
+        let parts = parts_from(PROXY, Some("198.51.100.1, 203.0.113.7, 10.0.0.2"));
+        let trusted = [IpAddr::from(PROXY), IpAddr::from([10, 0, 0, 2])];
+
+        assert_eq!(extract_ip(&parts, &trusted), Some("203.0.113.7".to_owned()));
+    }
+
+    #[test]
+    fn extract_ip_ignores_forwarded_header_from_untrusted_peer() {
+        let parts = parts_from([192, 0, 2, 9], Some("203.0.113.7"));
+
+        assert_eq!(
+            extract_ip(&parts, &[IpAddr::from(PROXY)]),
+            Some("192.0.2.9".to_owned())
+        );
+    }
+
+    #[test]
+    fn extract_ip_stops_at_malformed_hop() {
+        let parts = parts_from(PROXY, Some("203.0.113.7, not-an-ip"));
+
+        assert_eq!(
+            extract_ip(&parts, &[IpAddr::from(PROXY)]),
+            Some("10.0.0.1".to_owned())
+        );
+    }
+
+    #[test]
+    fn extract_ip_falls_back_to_peer_address() {
+        let parts = parts_from(PROXY, None);
+
+        assert_eq!(
+            extract_ip(&parts, &[IpAddr::from(PROXY)]),
+            Some("10.0.0.1".to_owned())
+        );
+    }
+}
@@ -1,13 +1,39 @@
+use axum_extra::extract::cookie::SameSite;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use dotenvy::dotenv;
 use lazy_static::lazy_static;
 use secrecy::Secret;
-use std::env as std_env;
+use std::{collections::HashMap, env as std_env, net::IpAddr};
+
+use super::cookies::CookieConfig;
 
 lazy_static! {
     pub static ref JWT_SECRET: Secret<String> = set_token();
+    pub static ref JWT_PRIVATE_KEY: Option<Secret<String>> = set_private_key();
+    pub static ref JWT_KEY_ID: String = set_key_id();
+    pub static ref JWT_RETIRED_KEYS: Option<Secret<String>> = set_retired_keys();
+    pub static ref JWT_KEY_OVERLAP_SECONDS: i64 = set_key_overlap_seconds();
+    pub static ref JWT_ISSUER: String = set_issuer();
+    pub static ref JWT_AUDIENCES: Vec<String> = set_audiences();
+    pub static ref JWT_LEEWAY_SECONDS: u64 = set_leeway_seconds();
     pub static ref DATABASE_URL: Secret<String> = set_db_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
     pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();
+    pub static ref COOKIE_CONFIG: CookieConfig = set_cookie_config();
+    pub static ref ALLOWED_ORIGINS: Vec<String> = set_allowed_origins();
+    pub static ref CSRF_EXEMPT_PATHS: Vec<String> = set_csrf_exempt_paths();
+    pub static ref INTROSPECTION_CLIENTS: HashMap<String, Secret<String>> =
+        set_introspection_clients();
+    pub static ref ADMIN_CLIENTS: HashMap<String, Secret<String>> = set_admin_clients();
+    pub static ref AUTH_SERVICE_URL: String = set_auth_service_url();
+    pub static ref REQUIRE_VERIFIED_EMAIL: bool = set_require_verified_email();
+    pub static ref LOGIN_LOCKOUT_THRESHOLD: u32 = set_login_lockout_threshold();
+    pub static ref LOGIN_LOCKOUT_SECONDS: u64 = set_login_lockout_seconds();
+    pub static ref MAX_2FA_ATTEMPTS: u32 = set_max_2fa_attempts();
+    pub static ref RESEND_2FA_COOLDOWN_SECONDS: u64 = set_resend_2fa_cooldown_seconds();
+    pub static ref MAX_2FA_RESENDS: u32 = set_max_2fa_resends();
+    pub static ref TOTP_ENCRYPTION_KEY: Secret<Vec<u8>> = set_totp_encryption_key();
+    pub static ref TRUSTED_PROXIES: Vec<IpAddr> = set_trusted_proxies();
 }
 
 fn set_token() -> Secret<String> {
@@ -19,6 +45,81 @@ fn set_token() -> Secret<String> {
     Secret::new(secret)
 }
 
+fn set_private_key() -> Option<Secret<String>> {
+    dotenv().ok();
+    let path = std_env::var(env::JWT_PRIVATE_KEY_PATH_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())?;
+    let pem = std::fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("Failed to read JWT private key from {}.", path));
+    Some(Secret::new(pem))
+}
+
+fn set_key_id() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_KEY_ID_ENV_VAR)
+        .ok()
+        .filter(|kid| !kid.is_empty())
+        .unwrap_or(DEFAULT_JWT_KEY_ID.to_owned())
+}
+
+fn set_retired_keys() -> Option<Secret<String>> {
+    dotenv().ok();
+    let path = std_env::var(env::JWT_RETIRED_KEYS_PATH_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())?;
+    let keys = std::fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("Failed to read retired JWT keys from {}.", path));
+    Some(Secret::new(keys))
+}
+
+fn set_key_overlap_seconds() -> i64 {
+    dotenv().ok();
+    std_env::var(env::JWT_KEY_OVERLAP_SECONDS_ENV_VAR)
+        .ok()
+        .map(|seconds| {
+            seconds
+                .parse()
+                .expect("JWT_KEY_OVERLAP_SECONDS must be a whole number of seconds.")
+        })
+        .unwrap_or(DEFAULT_JWT_KEY_OVERLAP_SECONDS)
+}
+
+fn set_issuer() -> String {
+    dotenv().ok();
+    std_env::var(env::JWT_ISSUER_ENV_VAR)
+        .ok()
+        .filter(|issuer| !issuer.is_empty())
+        .unwrap_or(DEFAULT_JWT_ISSUER.to_owned())
+}
+
+// The first audience is used for clients that do not ask for a specific one
+fn set_audiences() -> Vec<String> {
+    dotenv().ok();
+    let audiences: Vec<String> = std_env::var(env::JWT_AUDIENCES_ENV_VAR)
+        .unwrap_or(DEFAULT_JWT_AUDIENCE.to_owned())
+        .split(',')
+        .map(|audience| audience.trim().to_owned())
+        .filter(|audience| !audience.is_empty())
+        .collect();
+    if audiences.is_empty() {
+        panic!("JWT_AUDIENCES must contain at least one audience.");
+    }
+    audiences
+}
+
+fn set_leeway_seconds() -> u64 {
+    dotenv().ok();
+    std_env::var(env::JWT_LEEWAY_SECONDS_ENV_VAR)
+        .ok()
+        .map(|seconds| {
+            seconds
+                .parse()
+                .expect("JWT_LEEWAY_SECONDS must be a whole number of seconds.")
+        })
+        .unwrap_or(DEFAULT_JWT_LEEWAY_SECONDS)
+}
+
 fn set_db_url() -> Secret<String> {
     dotenv().ok();
     Secret::new(std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set."))
@@ -36,15 +137,267 @@ fn set_postmark_auth_token() -> Secret<String> {
     )
 }
 
+fn set_cookie_config() -> CookieConfig {
+    dotenv().ok();
+    let defaults = CookieConfig::default();
+
+    let secure = parse_bool_env(env::COOKIE_SECURE_ENV_VAR, defaults.secure);
+    let host_prefix = parse_bool_env(env::COOKIE_HOST_PREFIX_ENV_VAR, defaults.host_prefix);
+    let same_site = match std_env::var(env::COOKIE_SAME_SITE_ENV_VAR)
+        .unwrap_or_default()
+        .to_lowercase()
+        .as_str()
+    {
+        "" => defaults.same_site,
+        "lax" => SameSite::Lax,
+        "strict" => SameSite::Strict,
+        "none" => SameSite::None,
+        _ => panic!("COOKIE_SAME_SITE must be one of lax, strict or none."),
+    };
+    let domain = std_env::var(env::COOKIE_DOMAIN_ENV_VAR)
+        .ok()
+        .filter(|domain| !domain.is_empty());
+    let path = std_env::var(env::COOKIE_PATH_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())
+        .unwrap_or(defaults.path);
+
+    CookieConfig::new(secure, same_site, domain, path, host_prefix)
+        .unwrap_or_else(|e| panic!("Invalid cookie configuration: {}.", e))
+}
+
+// Origins of the pages allowed to call us with credentials, used for both CORS and CSRF checks
+fn set_allowed_origins() -> Vec<String> {
+    dotenv().ok();
+    std_env::var(env::ALLOWED_ORIGINS_ENV_VAR)
+        .unwrap_or(DEFAULT_ALLOWED_ORIGINS.to_owned())
+        .split(',')
+        .map(|origin| origin.trim().trim_end_matches('/').to_owned())
+        .filter(|origin| !origin.is_empty())
+        .collect()
+}
+
+fn set_csrf_exempt_paths() -> Vec<String> {
+    dotenv().ok();
+    std_env::var(env::CSRF_EXEMPT_PATHS_ENV_VAR)
+        .unwrap_or_default()
+        .split(',')
+        .map(|path| path.trim().to_owned())
+        .filter(|path| !path.is_empty())
+        .collect()
+}
+
+fn parse_bool_env(name: &str, default: bool) -> bool {
+    std_env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be true or false.", name))
+        })
+        .unwrap_or(default)
+}
+
+// Backends allowed to call /introspect
+fn set_introspection_clients() -> HashMap<String, Secret<String>> {
+    dotenv().ok();
+    parse_clients(env::INTROSPECTION_CLIENTS_ENV_VAR)
+}
+
+// Backends allowed to call the /admin routes
+fn set_admin_clients() -> HashMap<String, Secret<String>> {
+    dotenv().ok();
+    parse_clients(env::ADMIN_CLIENTS_ENV_VAR)
+}
+
+// Clients are given as comma-separated client_id:secret pairs
+fn parse_clients(name: &str) -> HashMap<String, Secret<String>> {
+    std_env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(|client| client.trim())
+        .filter(|client| !client.is_empty())
+        .map(|client| {
+            let (client_id, secret) = client
+                .split_once(':')
+                .unwrap_or_else(|| panic!("{} entries must look like client_id:secret.", name));
+            (client_id.to_owned(), Secret::new(secret.to_owned()))
+        })
+        .collect()
+}
+
+// Public base URL of this service, used to build the links we send by email
+fn set_auth_service_url() -> String {
+    dotenv().ok();
+    std_env::var(env::AUTH_SERVICE_URL_ENV_VAR)
+        .ok()
+        .map(|url| url.trim().trim_end_matches('/').to_owned())
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_AUTH_SERVICE_URL.to_owned())
+}
+
+// Whether users must verify their email address before they can log in
+fn set_require_verified_email() -> bool {
+    dotenv().ok();
+    parse_bool_env(env::REQUIRE_VERIFIED_EMAIL_ENV_VAR, false)
+}
+
+// How many failed logins in a row lock an account
+fn set_login_lockout_threshold() -> u32 {
+    dotenv().ok();
+    let threshold = std_env::var(env::LOGIN_LOCKOUT_THRESHOLD_ENV_VAR)
+        .ok()
+        .filter(|threshold| !threshold.is_empty())
+        .map(|threshold| {
+            threshold
+                .parse()
+                .expect("LOGIN_LOCKOUT_THRESHOLD must be a whole number.")
+        })
+        .unwrap_or(DEFAULT_LOGIN_LOCKOUT_THRESHOLD);
+    if threshold == 0 {
+        panic!("LOGIN_LOCKOUT_THRESHOLD must be at least 1.");
+    }
+    threshold
+}
+
+// How long the first lock lasts. Each further failure doubles it.
+fn set_login_lockout_seconds() -> u64 {
+    dotenv().ok();
+    std_env::var(env::LOGIN_LOCKOUT_SECONDS_ENV_VAR)
+        .ok()
+        .filter(|seconds| !seconds.is_empty())
+        .map(|seconds| {
+            seconds
+                .parse()
+                .expect("LOGIN_LOCKOUT_SECONDS must be a whole number of seconds.")
+        })
+        .unwrap_or(DEFAULT_LOGIN_LOCKOUT_SECONDS)
+}
+
+// Wrong codes allowed per login attempt before the user has to log in again
+fn set_max_2fa_attempts() -> u32 {
+    dotenv().ok();
+    let attempts = std_env::var(env::MAX_2FA_ATTEMPTS_ENV_VAR)
+        .ok()
+        .filter(|attempts| !attempts.is_empty())
+        .map(|attempts| {
+            attempts
+                .parse()
+                .expect("MAX_2FA_ATTEMPTS must be a whole number.")
+        })
+        .unwrap_or(DEFAULT_MAX_2FA_ATTEMPTS);
+    if attempts == 0 {
+        panic!("MAX_2FA_ATTEMPTS must be at least 1.");
+    }
+    attempts
+}
+
+fn set_resend_2fa_cooldown_seconds() -> u64 {
+    dotenv().ok();
+    std_env::var(env::RESEND_2FA_COOLDOWN_SECONDS_ENV_VAR)
+        .ok()
+        .filter(|seconds| !seconds.is_empty())
+        .map(|seconds| {
+            seconds
+                .parse()
+                .expect("RESEND_2FA_COOLDOWN_SECONDS must be a whole number of seconds.")
+        })
+        .unwrap_or(DEFAULT_RESEND_2FA_COOLDOWN_SECONDS)
+}
+
+fn set_max_2fa_resends() -> u32 {
+    dotenv().ok();
+    std_env::var(env::MAX_2FA_RESENDS_ENV_VAR)
+        .ok()
+        .filter(|resends| !resends.is_empty())
+        .map(|resends| {
+            resends
+                .parse()
+                .expect("MAX_2FA_RESENDS must be a whole number.")
+        })
+        .unwrap_or(DEFAULT_MAX_2FA_RESENDS)
+}
+
+// TOTP secrets are stored encrypted with this key, a base64 encoded 32 byte value
+fn set_totp_encryption_key() -> Secret<Vec<u8>> {
+    dotenv().ok();
+    let key =
+        std_env::var(env::TOTP_ENCRYPTION_KEY_ENV_VAR).expect("TOTP_ENCRYPTION_KEY must be set.");
+    let key = STANDARD
+        .decode(key.trim())
+        .expect("TOTP_ENCRYPTION_KEY must be base64 encoded.");
+    if key.len() != 32 {
+        panic!("TOTP_ENCRYPTION_KEY must be 32 bytes long.");
+    }
+    Secret::new(key)
+}
+
+// Proxies whose X-Forwarded-For entries are believed. With none set, the
+// header is ignored and the peer address is used.
+fn set_trusted_proxies() -> Vec<IpAddr> {
+    dotenv().ok();
+    std_env::var(env::TRUSTED_PROXIES_ENV_VAR)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|proxy| !proxy.is_empty())
+        .map(|proxy| {
+            proxy
+                .parse()
+                .expect("TRUSTED_PROXIES must be a list of IP addresses.")
+        })
+        .collect()
+}
+
 pub mod env {
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_PRIVATE_KEY_PATH_ENV_VAR: &str = "JWT_PRIVATE_KEY_PATH";
+    pub const JWT_KEY_ID_ENV_VAR: &str = "JWT_KEY_ID";
+    pub const JWT_RETIRED_KEYS_PATH_ENV_VAR: &str = "JWT_RETIRED_KEYS_PATH";
+    pub const JWT_KEY_OVERLAP_SECONDS_ENV_VAR: &str = "JWT_KEY_OVERLAP_SECONDS";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCES_ENV_VAR: &str = "JWT_AUDIENCES";
+    pub const JWT_LEEWAY_SECONDS_ENV_VAR: &str = "JWT_LEEWAY_SECONDS";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const INTROSPECTION_CLIENTS_ENV_VAR: &str = "INTROSPECTION_CLIENTS";
+    pub const ADMIN_CLIENTS_ENV_VAR: &str = "ADMIN_CLIENTS";
+    pub const COOKIE_SECURE_ENV_VAR: &str = "COOKIE_SECURE";
+    pub const COOKIE_SAME_SITE_ENV_VAR: &str = "COOKIE_SAME_SITE";
+    pub const COOKIE_DOMAIN_ENV_VAR: &str = "COOKIE_DOMAIN";
+    pub const COOKIE_PATH_ENV_VAR: &str = "COOKIE_PATH";
+    pub const COOKIE_HOST_PREFIX_ENV_VAR: &str = "COOKIE_HOST_PREFIX";
+    pub const ALLOWED_ORIGINS_ENV_VAR: &str = "ALLOWED_ORIGINS";
+    pub const CSRF_EXEMPT_PATHS_ENV_VAR: &str = "CSRF_EXEMPT_PATHS";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
+    pub const REQUIRE_VERIFIED_EMAIL_ENV_VAR: &str = "REQUIRE_VERIFIED_EMAIL";
+    pub const LOGIN_LOCKOUT_THRESHOLD_ENV_VAR: &str = "LOGIN_LOCKOUT_THRESHOLD";
+    pub const LOGIN_LOCKOUT_SECONDS_ENV_VAR: &str = "LOGIN_LOCKOUT_SECONDS";
+    pub const MAX_2FA_ATTEMPTS_ENV_VAR: &str = "MAX_2FA_ATTEMPTS";
+    pub const RESEND_2FA_COOLDOWN_SECONDS_ENV_VAR: &str = "RESEND_2FA_COOLDOWN_SECONDS";
+    pub const MAX_2FA_RESENDS_ENV_VAR: &str = "MAX_2FA_RESENDS";
+    pub const TOTP_ENCRYPTION_KEY_ENV_VAR: &str = "TOTP_ENCRYPTION_KEY";
+    pub const TRUSTED_PROXIES_ENV_VAR: &str = "TRUSTED_PROXIES";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const DEFAULT_JWT_KEY_ID: &str = "default";
+// Retired keys keep verifying tokens for a day, well past the lifetime of any JWT they signed
+pub const DEFAULT_JWT_KEY_OVERLAP_SECONDS: i64 = 60 * 60 * 24;
+pub const DEFAULT_JWT_ISSUER: &str = "auth-service";
+pub const DEFAULT_JWT_AUDIENCE: &str = "app-service";
+pub const DEFAULT_JWT_LEEWAY_SECONDS: u64 = 60;
+pub const DEFAULT_ALLOWED_ORIGINS: &str = "http://localhost:8000,https://lgr.wallys.world";
+pub const DEFAULT_AUTH_SERVICE_URL: &str = "http://localhost:3000";
+pub const DEFAULT_LOGIN_LOCKOUT_THRESHOLD: u32 = 5;
+pub const DEFAULT_LOGIN_LOCKOUT_SECONDS: u64 = 60;
+pub const DEFAULT_MAX_2FA_ATTEMPTS: u32 = 5;
+pub const DEFAULT_RESEND_2FA_COOLDOWN_SECONDS: u64 = 30;
+pub const DEFAULT_MAX_2FA_RESENDS: u32 = 3;
 
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
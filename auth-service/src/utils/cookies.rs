@@ -0,0 +1,158 @@
+use axum_extra::extract::cookie::{Cookie, SameSite};
+
+// Attributes shared by every cookie the auth service sets, so the cookies we
+// remove on logout always match the ones we created.
+#[derive(Debug, Clone)]
+pub struct CookieConfig {
+    pub secure: bool,
+    pub same_site: SameSite,
+    pub domain: Option<String>,
+    pub path: String,
+    // `__Host-` cookies must be Secure, have Path=/ and no Domain. Browsers
+    // then refuse to let subdomains or plain HTTP pages overwrite them.
+    pub host_prefix: bool,
+}
+
+const HOST_PREFIX: &str = "__Host-";
+
+impl CookieConfig {
+    pub fn new(
+        secure: bool,
+        same_site: SameSite,
+        domain: Option<String>,
+        path: String,
+        host_prefix: bool,
+    ) -> Result<Self, String> {
+        if host_prefix && (domain.is_some() || path != "/") {
+            return Err("__Host- cookies cannot set a Domain and must use Path=/".to_owned());
+        }
+
+        if same_site == SameSite::None && !(secure || host_prefix) {
+            return Err("SameSite=None cookies must be Secure".to_owned());
+        }
+
+        Ok(Self {
+            secure: secure || host_prefix,
+            same_site,
+            domain,
+            path,
+            host_prefix,
+        })
+    }
+
+    pub fn name(&self, name: &str) -> String {
+        if self.host_prefix {
+            format!("{}{}", HOST_PREFIX, name)
+        } else {
+            name.to_owned()
+        }
+    }
+
+    pub fn build(&self, name: &str, value: String, max_age: time::Duration) -> Cookie<'static> {
+        let mut cookie = Cookie::build((self.name(name), value))
+            .path(self.path.clone())
+            .http_only(true) // prevent JavaScript from accessing the cookie
+            .secure(self.secure)
+            .same_site(self.same_site)
+            .max_age(max_age) // expire together with the token inside it
+            .build();
+
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        cookie
+    }
+
+    // A cookie is only deleted when the removal has the same name, path and domain
+    pub fn removal(&self, name: &str) -> Cookie<'static> {
+        let mut cookie = self.build(name, String::new(), time::Duration::ZERO);
+        cookie.make_removal();
+        cookie
+    }
+}
+
+impl Default for CookieConfig {
+    fn default() -> Self {
+        Self {
+            secure: false,
+            same_site: SameSite::Lax,
+            domain: None,
+            path: "/".to_owned(),
+            host_prefix: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_applies_configured_attributes() {
+        let config = CookieConfig::new(
+            true,
+            SameSite::Strict,
+            Some("example.com".to_owned()),
+            "/auth".to_owned(),
+            false,
+        )
+        .unwrap();
+        let cookie = config.build("jwt", "token".to_owned(), time::Duration::seconds(600));
+        assert_eq!(cookie.name(), "jwt");
+        assert_eq!(cookie.value(), "token");
+        assert_eq!(cookie.path(), Some("/auth"));
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(cookie.max_age(), Some(time::Duration::seconds(600)));
+    }
+
+    #[test]
+    fn host_prefix_forces_secure_and_renames_cookie() {
+        let config = CookieConfig::new(false, SameSite::Lax, None, "/".to_owned(), true).unwrap();
+        let cookie = config.build("jwt", "token".to_owned(), time::Duration::seconds(600));
+        assert_eq!(cookie.name(), "__Host-jwt");
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.domain(), None);
+    }
+
+    #[test]
+    fn host_prefix_rejects_domain_and_path() {
+        assert!(CookieConfig::new(
+            true,
+            SameSite::Lax,
+            Some("example.com".to_owned()),
+            "/".to_owned(),
+            true
+        )
+        .is_err());
+        assert!(CookieConfig::new(true, SameSite::Lax, None, "/auth".to_owned(), true).is_err());
+    }
+
+    #[test]
+    fn same_site_none_requires_secure() {
+        assert!(CookieConfig::new(false, SameSite::None, None, "/".to_owned(), false).is_err());
+        assert!(CookieConfig::new(true, SameSite::None, None, "/".to_owned(), false).is_ok());
+    }
+
+    #[test]
+    fn removal_matches_original_cookie() {
+        let config = CookieConfig::new(
+            true,
+            SameSite::Strict,
+            Some("example.com".to_owned()),
+            "/auth".to_owned(),
+            false,
+        )
+        .unwrap();
+        let cookie = config.removal("jwt");
+        assert_eq!(cookie.name(), "jwt");
+        assert_eq!(cookie.value(), "");
+        assert_eq!(cookie.path(), Some("/auth"));
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.max_age(), Some(time::Duration::ZERO));
+    }
+}
@@ -0,0 +1,160 @@
+use axum::{
+    extract::Request,
+    http::{
+        header::{HOST, ORIGIN, REFERER},
+        HeaderMap, Method,
+    },
+    middleware::Next,
+    response::Response,
+};
+use axum_extra::extract::CookieJar;
+
+use crate::domain::AuthAPIError;
+
+use super::constants::{
+    ALLOWED_ORIGINS, COOKIE_CONFIG, CSRF_EXEMPT_PATHS, JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME,
+};
+
+// Browsers attach our cookies to cross-site requests too, so any state-changing
+// request that authenticates with a cookie must come from a page we trust.
+// Requests authenticated with a bearer token cannot be forged this way.
+#[tracing::instrument(name = "CSRF check", skip_all)]
+pub async fn verify_origin(request: Request, next: Next) -> Result<Response, AuthAPIError> {
+    if is_safe_method(request.method())
+        || CSRF_EXEMPT_PATHS.contains(&request.uri().path().to_owned())
+        || !has_auth_cookie(request.headers())
+    {
+        return Ok(next.run(request).await);
+    }
+
+    if !is_trusted_origin(request.headers(), &ALLOWED_ORIGINS) {
+        tracing::warn!("rejected cookie-authenticated request from an untrusted origin");
+        return Err(AuthAPIError::UntrustedOrigin);
+    }
+
+    Ok(next.run(request).await)
+}
+
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+fn has_auth_cookie(headers: &HeaderMap) -> bool {
+    let jar = CookieJar::from_headers(headers);
+    jar.get(&COOKIE_CONFIG.name(JWT_COOKIE_NAME)).is_some()
+        || jar
+            .get(&COOKIE_CONFIG.name(REFRESH_TOKEN_COOKIE_NAME))
+            .is_some()
+}
+
+// Uses the Origin header, falling back to the Referer for browsers that leave it
+// out. A request with neither cannot be attributed to a page and is rejected.
+fn is_trusted_origin(headers: &HeaderMap, allowed_origins: &[String]) -> bool {
+    let origin = match headers.get(ORIGIN) {
+        Some(origin) => origin.to_str().ok().map(|origin| origin.to_owned()),
+        None => headers
+            .get(REFERER)
+            .and_then(|referer| referer.to_str().ok())
+            .and_then(referer_origin),
+    };
+
+    let Some(origin) = origin else {
+        return false;
+    };
+
+    if allowed_origins.contains(&origin) {
+        return true;
+    }
+
+    // Pages served by the auth service itself, like assets/app.js
+    let host = headers.get(HOST).and_then(|host| host.to_str().ok());
+    match (origin.split_once("://"), host) {
+        (Some((scheme, origin_host)), Some(host)) => {
+            matches!(scheme, "http" | "https") && origin_host == host
+        }
+        _ => false,
+    }
+}
+
+fn referer_origin(referer: &str) -> Option<String> {
+    let (scheme, rest) = referer.split_once("://")?;
+    let host = rest.split(['/', '?', '#']).next()?;
+    if host.is_empty() {
+        return None;
+    }
+    Some(format!("{}://{}", scheme, host))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn headers(values: &[(axum::http::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in values {
+            headers.insert(name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn allowed() -> Vec<String> {
+        vec!["http://localhost:8000".to_owned()]
+    }
+
+    #[test]
+    fn allows_configured_origin() {
+        let headers = headers(&[(ORIGIN, "http://localhost:8000"), (HOST, "localhost:3000")]);
+        assert!(is_trusted_origin(&headers, &allowed()));
+    }
+
+    #[test]
+    fn allows_same_origin() {
+        let headers = headers(&[(ORIGIN, "http://localhost:3000"), (HOST, "localhost:3000")]);
+        assert!(is_trusted_origin(&headers, &allowed()));
+    }
+
+    #[test]
+    fn rejects_foreign_origin() {
+        let headers = headers(&[(ORIGIN, "https://evil.example"), (HOST, "localhost:3000")]);
+        assert!(!is_trusted_origin(&headers, &allowed()));
+    }
+
+    #[test]
+    fn falls_back_to_referer() {
+        let trusted = headers(&[
+            (REFERER, "http://localhost:8000/some/page?x=1"),
+            (HOST, "localhost:3000"),
+        ]);
+        assert!(is_trusted_origin(&trusted, &allowed()));
+
+        let untrusted = headers(&[
+            (REFERER, "https://evil.example/localhost:8000"),
+            (HOST, "localhost:3000"),
+        ]);
+        assert!(!is_trusted_origin(&untrusted, &allowed()));
+    }
+
+    #[test]
+    fn rejects_missing_origin_and_referer() {
+        let headers = headers(&[(HOST, "localhost:3000")]);
+        assert!(!is_trusted_origin(&headers, &allowed()));
+    }
+
+    #[test]
+    fn detects_auth_cookies() {
+        assert!(has_auth_cookie(&headers(&[(
+            axum::http::header::COOKIE,
+            "jwt=abc"
+        )])));
+        assert!(has_auth_cookie(&headers(&[(
+            axum::http::header::COOKIE,
+            "refresh_token=abc"
+        )])));
+        assert!(!has_auth_cookie(&headers(&[(
+            axum::http::header::COOKIE,
+            "other=abc"
+        )])));
+    }
+}
@@ -0,0 +1,281 @@
+use color_eyre::eyre::{eyre, Context, Result};
+use jsonwebtoken::{decode, decode_header, encode};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app_state::BannedTokenStoreType,
+    domain::{Email, EmailClient},
+};
+
+use super::{
+    auth::now_timestamp,
+    constants::{AUTH_SERVICE_URL, JWT_ISSUER},
+    keys::KEY_RING,
+};
+
+// What an emailed link lets its holder do. Each purpose is its own audience,
+// so a token minted for one flow is useless for any other, and as a JWT.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmailTokenPurpose {
+    VerifyEmail,
+    ResetPassword,
+    ConfirmEmailChange,
+    UndoEmailChange,
+    UnlockAccount,
+}
+
+impl EmailTokenPurpose {
+    fn audience(&self) -> &'static str {
+        match self {
+            Self::VerifyEmail => "verify-email",
+            Self::ResetPassword => "reset-password",
+            Self::ConfirmEmailChange => "confirm-email-change",
+            Self::UndoEmailChange => "undo-email-change",
+            Self::UnlockAccount => "unlock-account",
+        }
+    }
+
+    fn ttl_seconds(&self) -> u64 {
+        match self {
+            Self::VerifyEmail => 60 * 60 * 24,         // 1 day
+            Self::ResetPassword => 60 * 60,            // 1 hour
+            Self::ConfirmEmailChange => 60 * 60 * 24,  // 1 day
+            Self::UndoEmailChange => 60 * 60 * 24 * 7, // 1 week
+            Self::UnlockAccount => 60 * 60 * 24,       // 1 day
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailTokenClaims {
+    pub sub: String,
+    pub exp: u64,
+    pub iat: u64,
+    pub jti: String,
+    pub iss: String,
+    pub aud: String,
+    // Address the account is moving to, for email change tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_email: Option<String>,
+    // Id of the account being moved, for email change tokens. Either address
+    // may belong to another account by the time the link is followed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<Uuid>,
+    // Token that redeeming this one cancels, for email change undo tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancels: Option<String>,
+}
+
+impl EmailTokenClaims {
+    fn new(email: &Email, purpose: EmailTokenPurpose) -> Self {
+        let iat = now_timestamp();
+
+        Self {
+            sub: email.as_ref().expose_secret().to_owned(),
+            exp: iat + purpose.ttl_seconds(),
+            iat,
+            jti: uuid::Uuid::new_v4().to_string(),
+            iss: JWT_ISSUER.to_owned(),
+            aud: purpose.audience().to_owned(),
+            new_email: None,
+            user_id: None,
+            cancels: None,
+        }
+    }
+}
+
+#[tracing::instrument(name = "Generating email token", skip_all)]
+pub fn generate_email_token(email: &Email, purpose: EmailTokenPurpose) -> Result<Secret<String>> {
+    sign_email_token(&EmailTokenClaims::new(email, purpose))
+}
+
+// Sent on signup, and again from /resend-verification if that one never arrived
+#[tracing::instrument(name = "Sending verification email", skip_all)]
+pub async fn send_verification_email(
+    email_client: &(dyn EmailClient + Send + Sync),
+    email: &Email,
+) -> Result<()> {
+    let token = generate_email_token(email, EmailTokenPurpose::VerifyEmail)?;
+
+    email_client
+        .send_email(
+            email,
+            "Verify your email address",
+            &format!(
+                "Confirm your email address by following this link: {}/verify-email?token={}",
+                AUTH_SERVICE_URL.as_str(),
+                token.expose_secret()
+            ),
+        )
+        .await
+}
+
+// Returns the confirmation token for the new address and the undo token for
+// the old one. Undoing also cancels the confirmation if it has not happened yet.
+#[tracing::instrument(name = "Generating email change tokens", skip_all)]
+pub fn generate_email_change_tokens(
+    user_id: Uuid,
+    email: &Email,
+    new_email: &Email,
+) -> Result<(Secret<String>, Secret<String>)> {
+    let mut confirm = EmailTokenClaims::new(email, EmailTokenPurpose::ConfirmEmailChange);
+    confirm.new_email = Some(new_email.as_ref().expose_secret().to_owned());
+    confirm.user_id = Some(user_id);
+
+    let mut undo = EmailTokenClaims::new(email, EmailTokenPurpose::UndoEmailChange);
+    undo.new_email = confirm.new_email.clone();
+    undo.user_id = Some(user_id);
+    undo.cancels = Some(confirm.jti.clone());
+
+    Ok((sign_email_token(&confirm)?, sign_email_token(&undo)?))
+}
+
+fn sign_email_token(claims: &EmailTokenClaims) -> Result<Secret<String>> {
+    let key = KEY_RING.active_key();
+    let token = encode(&key.header(), claims, key.encoding_key())
+        .wrap_err("failed to create email token")?;
+
+    Ok(Secret::new(token))
+}
+
+// Checks the token and uses it up, so the link it came in only works once
+#[tracing::instrument(name = "Redeeming email token", skip_all)]
+pub async fn redeem_email_token(
+    token: &Secret<String>,
+    purpose: EmailTokenPurpose,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<EmailTokenClaims> {
+    let header = decode_header(token.expose_secret()).wrap_err("failed to decode token header")?;
+    let key = KEY_RING
+        .verification_key(header.kid.as_deref())
+        .ok_or(eyre!("token was not signed by a known key"))?;
+
+    let mut validation = key.validation();
+    validation.set_issuer(&[JWT_ISSUER.as_str()]);
+    validation.set_audience(&[purpose.audience()]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+    let claims = decode::<EmailTokenClaims>(token.expose_secret(), key.decoding_key(), &validation)
+        .map(|data| data.claims)
+        .wrap_err("failed to decode email token")?;
+
+    let mut banned_token_store = banned_token_store.write().await;
+
+    if banned_token_store.contains_token(&claims.jti).await? {
+        return Err(eyre!("email token has already been used"));
+    }
+
+    banned_token_store
+        .add_token(claims.jti.clone(), claims.exp)
+        .await?;
+
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::RwLock;
+
+    use crate::{
+        domain::data_stores::BannedTokenStore,
+        services::hashset_banned_token_store::HashsetBannedTokenStore,
+    };
+
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_owned())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn redeem_email_token_should_return_claims() {
+        let token = generate_email_token(&email(), EmailTokenPurpose::VerifyEmail).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let claims = redeem_email_token(&token, EmailTokenPurpose::VerifyEmail, banned_token_store)
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.aud, "verify-email");
+    }
+
+    #[tokio::test]
+    async fn redeem_email_token_should_only_work_once() {
+        let token = generate_email_token(&email(), EmailTokenPurpose::VerifyEmail).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let result = redeem_email_token(
+            &token,
+            EmailTokenPurpose::VerifyEmail,
+            banned_token_store.clone(),
+        )
+        .await;
+        assert!(result.is_ok());
+
+        let result =
+            redeem_email_token(&token, EmailTokenPurpose::VerifyEmail, banned_token_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn redeem_email_token_should_reject_tampered_token() {
+        let token = generate_email_token(&email(), EmailTokenPurpose::VerifyEmail).unwrap();
+        let tampered = Secret::new(format!("{}x", token.expose_secret()));
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let result = redeem_email_token(
+            &tampered,
+            EmailTokenPurpose::VerifyEmail,
+            banned_token_store,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn redeem_email_token_should_reject_other_purpose() {
+        let token = generate_email_token(&email(), EmailTokenPurpose::VerifyEmail).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let result =
+            redeem_email_token(&token, EmailTokenPurpose::ResetPassword, banned_token_store).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn email_change_undo_token_should_cancel_confirmation() {
+        let new_email = Email::parse(Secret::new("new@example.com".to_owned())).unwrap();
+        let (confirm, undo) =
+            generate_email_change_tokens(Uuid::nil(), &email(), &new_email).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let claims = redeem_email_token(
+            &undo,
+            EmailTokenPurpose::UndoEmailChange,
+            banned_token_store.clone(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(claims.new_email.as_deref(), Some("new@example.com"));
+        assert_eq!(claims.user_id, Some(Uuid::nil()));
+
+        banned_token_store
+            .write()
+            .await
+            .add_token(claims.cancels.unwrap(), claims.exp)
+            .await
+            .unwrap();
+
+        let result = redeem_email_token(
+            &confirm,
+            EmailTokenPurpose::ConfirmEmailChange,
+            banned_token_store,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+}
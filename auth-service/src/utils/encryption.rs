@@ -0,0 +1,117 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+use secrecy::{ExposeSecret, Secret};
+
+// Encrypts with AES-256-GCM. The random nonce is stored in front of the
+// ciphertext. `context` is authenticated but not stored, so a value only
+// decrypts with the same context, e.g. the id of the user it belongs to.
+pub fn encrypt(
+    key: &Secret<Vec<u8>>,
+    plaintext: &Secret<String>,
+    context: &[u8],
+) -> Result<String> {
+    let key = sealing_key(key)?;
+
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| eyre!("failed to generate nonce"))?;
+
+    let mut in_out = plaintext.expose_secret().as_bytes().to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(context),
+        &mut in_out,
+    )
+    .map_err(|_| eyre!("failed to encrypt"))?;
+
+    Ok(STANDARD.encode([nonce.as_slice(), &in_out].concat()))
+}
+
+pub fn decrypt(key: &Secret<Vec<u8>>, ciphertext: &str, context: &[u8]) -> Result<Secret<String>> {
+    let key = sealing_key(key)?;
+
+    let bytes = STANDARD
+        .decode(ciphertext)
+        .wrap_err("ciphertext is not base64")?;
+
+    if bytes.len() < NONCE_LEN {
+        return Err(eyre!("ciphertext is too short"));
+    }
+
+    let (nonce, in_out) = bytes.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| eyre!("invalid nonce"))?;
+
+    let mut in_out = in_out.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, Aad::from(context), &mut in_out)
+        .map_err(|_| eyre!("failed to decrypt"))?;
+
+    String::from_utf8(plaintext.to_vec())
+        .map(Secret::new)
+        .wrap_err("plaintext is not UTF-8")
+}
+
+fn sealing_key(key: &Secret<Vec<u8>>) -> Result<LessSafeKey> {
+    UnboundKey::new(&AES_256_GCM, key.expose_secret())
+        .map(LessSafeKey::new)
+        .map_err(|_| eyre!("encryption key must be 32 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> Secret<Vec<u8>> {
+        Secret::new(vec![byte; 32])
+    }
+
+    #[test]
+    fn round_trips() {
+        let plaintext = Secret::new("GEZDGNBVGY3TQOJQ".to_owned());
+
+        let ciphertext = encrypt(&key(1), &plaintext, b"user").unwrap();
+
+        assert!(!ciphertext.contains(plaintext.expose_secret()));
+        assert_eq!(
+            decrypt(&key(1), &ciphertext, b"user")
+                .unwrap()
+                .expose_secret(),
+            plaintext.expose_secret()
+        );
+    }
+
+    #[test]
+    fn nonce_is_random() {
+        let plaintext = Secret::new("GEZDGNBVGY3TQOJQ".to_owned());
+
+        assert_ne!(
+            encrypt(&key(1), &plaintext, b"user").unwrap(),
+            encrypt(&key(1), &plaintext, b"user").unwrap()
+        );
+    }
+
+    #[test]
+    fn wrong_key_or_context_fails() {
+        let ciphertext = encrypt(
+            &key(1),
+            &Secret::new("GEZDGNBVGY3TQOJQ".to_owned()),
+            b"user",
+        )
+        .unwrap();
+
+        assert!(decrypt(&key(2), &ciphertext, b"user").is_err());
+        assert!(decrypt(&key(1), &ciphertext, b"other user").is_err());
+    }
+
+    #[test]
+    fn short_key_is_rejected() {
+        let plaintext = Secret::new("GEZDGNBVGY3TQOJQ".to_owned());
+
+        assert!(encrypt(&Secret::new(vec![1; 16]), &plaintext, b"user").is_err());
+    }
+}
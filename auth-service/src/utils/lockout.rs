@@ -0,0 +1,167 @@
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        data_stores::UserStoreError,
+        user::{TwoFAMethod, User},
+        AuthAPIError, Email, Password, TotpCode,
+    },
+};
+
+use super::{
+    auth::now_timestamp,
+    constants::{AUTH_SERVICE_URL, LOGIN_LOCKOUT_SECONDS, LOGIN_LOCKOUT_THRESHOLD},
+    email_token::{generate_email_token, EmailTokenPurpose},
+};
+
+// Checks a password the same way /login does, so routes that ask for it again
+// cannot be used to keep guessing once the account is locked. Callers must not
+// hold the user store lock, which is only taken for the check itself and not
+// while the unlock email goes out.
+#[tracing::instrument(name = "Check password", skip_all)]
+pub async fn check_password(
+    state: &AppState,
+    email: &Email,
+    password: &Password,
+) -> Result<(), AuthAPIError> {
+    ensure_not_locked(state, email).await?;
+
+    let result = state
+        .user_store
+        .read()
+        .await
+        .validate_user(email, password)
+        .await;
+
+    match result {
+        Ok(()) => clear_failed_logins(state, email).await,
+        Err(UserStoreError::UnexpectedError(e)) => Err(AuthAPIError::UnexpectedError(e)),
+        // Guesses at unknown emails count too, so a lock says nothing about
+        // whether an account exists
+        Err(e) => {
+            let user_exists = e != UserStoreError::UserNotFound;
+            Err(record_failed_login(state, email, user_exists).await)
+        }
+    }
+}
+
+// Checks a code from the user's authenticator app, for routes that change
+// their second factor. Wrong codes count towards the same lockout as wrong
+// passwords, so a stolen session cannot keep guessing six digits. Returns the
+// step the code used, which the caller saves so it is not used again.
+//
+// Email codes are never accepted here. They belong to pending logins, and
+// using one for something else would quietly end that login.
+#[tracing::instrument(name = "Check TOTP code", skip_all)]
+pub async fn check_totp_code(
+    state: &AppState,
+    user: &User,
+    code: Secret<String>,
+) -> Result<u64, AuthAPIError> {
+    let email = &user.email;
+
+    let secret = match &user.totp_secret {
+        Some(secret) if user.requires_2fa && user.two_fa_method == TwoFAMethod::Totp => secret,
+        _ => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    let code = TotpCode::parse(code).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    ensure_not_locked(state, email).await?;
+
+    let Some(used_totp_step) = secret.verify(&code, now_timestamp(), user.totp_last_used_step)
+    else {
+        return Err(record_failed_login(state, email, true).await);
+    };
+
+    clear_failed_logins(state, email).await?;
+
+    Ok(used_totp_step)
+}
+
+pub async fn ensure_not_locked(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    let attempts = state
+        .login_attempt_store
+        .read()
+        .await
+        .get_attempts(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if attempts.is_locked(now_timestamp()) {
+        return Err(AuthAPIError::AccountLocked);
+    }
+
+    Ok(())
+}
+
+pub async fn clear_failed_logins(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .login_attempt_store
+        .write()
+        .await
+        .remove_attempts(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
+// Returns the error to answer the failed attempt with
+#[tracing::instrument(name = "Record failed login", skip_all)]
+pub async fn record_failed_login(
+    state: &AppState,
+    email: &Email,
+    user_exists: bool,
+) -> AuthAPIError {
+    let locked = {
+        let mut login_attempt_store = state.login_attempt_store.write().await;
+
+        let mut attempts = match login_attempt_store.get_attempts(email).await {
+            Ok(attempts) => attempts,
+            Err(e) => return AuthAPIError::UnexpectedError(e.into()),
+        };
+
+        let locked = attempts.record_failure(
+            now_timestamp(),
+            *LOGIN_LOCKOUT_THRESHOLD,
+            *LOGIN_LOCKOUT_SECONDS,
+        );
+
+        if let Err(e) = login_attempt_store.set_attempts(email, attempts).await {
+            return AuthAPIError::UnexpectedError(e.into());
+        }
+
+        locked
+    };
+
+    if !locked {
+        return AuthAPIError::IncorrectCredentials;
+    }
+
+    if user_exists {
+        // The lock stands either way, so a failed email must not hide it
+        if let Err(e) = send_unlock_email(state, email).await {
+            tracing::error!("failed to send account unlock email: {:?}", e);
+        }
+    }
+
+    AuthAPIError::AccountLocked
+}
+
+async fn send_unlock_email(state: &AppState, email: &Email) -> Result<()> {
+    let token = generate_email_token(email, EmailTokenPurpose::UnlockAccount)?;
+
+    state
+        .email_client
+        .send_email(
+            email,
+            "Your account has been locked",
+            &format!(
+                "Your account was locked after too many failed logins. If it was you, unlock it by following this link: {}/unlock-account?token={}",
+                *AUTH_SERVICE_URL,
+                token.expose_secret()
+            ),
+        )
+        .await
+}
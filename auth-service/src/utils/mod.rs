@@ -1,3 +1,12 @@
 pub mod auth;
+pub mod authenticated_user;
+pub mod client_credentials;
+pub mod client_info;
 pub mod constants;
+pub mod cookies;
+pub mod csrf;
+pub mod email_token;
+pub mod encryption;
+pub mod keys;
+pub mod lockout;
 pub mod tracing;
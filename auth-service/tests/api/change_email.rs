@@ -0,0 +1,301 @@
+use auth_service::{
+    domain::Email, utils::constants::JWT_COOKIE_NAME,
+    utils::email_token::generate_email_change_tokens,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, get_token_claims, TestApp};
+
+async fn signup(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    random_email
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&json!({
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+}
+
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let random_email = signup(app).await;
+
+    let response = login(app, &random_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (random_email, auth_token)
+}
+
+// The tokens name the account by the id in its auth token
+fn email_change_tokens(auth_token: &str, email: &str, new_email: &str) -> (String, String) {
+    let user_id = get_token_claims(auth_token)["sub"]
+        .as_str()
+        .and_then(|sub| Uuid::parse_str(sub).ok())
+        .expect("No user id in auth token");
+
+    let (confirm, undo) = generate_email_change_tokens(
+        user_id,
+        &Email::parse(Secret::new(email.to_owned())).unwrap(),
+        &Email::parse(Secret::new(new_email.to_owned())).unwrap(),
+    )
+    .unwrap();
+
+    (
+        confirm.expose_secret().to_owned(),
+        undo.expose_secret().to_owned(),
+    )
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_change_email(&json!({
+            "newEmail": get_random_email(),
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_password() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let response = app
+        .post_change_email(&json!({
+            "newEmail": get_random_email(),
+            "password": "wrongpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_409_if_email_taken() {
+    let mut app = TestApp::new().await;
+
+    let other_email = signup(&app).await;
+    signup_and_login(&app).await;
+
+    let response = app
+        .post_change_email(&json!({
+            "newEmail": other_email,
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 409);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_change_email_after_confirmation() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, auth_token) = signup_and_login(&app).await;
+    let new_email = get_random_email();
+
+    let response = app
+        .post_change_email(&json!({
+            "newEmail": new_email,
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Nothing changes until the new address is confirmed
+    assert_eq!(login(&app, &random_email).await.status().as_u16(), 200);
+
+    let (confirm_token, _) = email_change_tokens(&auth_token, &random_email, &new_email);
+
+    let response = app.post_confirm_email_change(&confirm_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(login(&app, &random_email).await.status().as_u16(), 401);
+
+    let response = login(&app, &new_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The account keeps its id, so tokens still name the same subject
+    let new_auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    assert_eq!(
+        get_token_claims(&new_auth_token)["sub"],
+        get_token_claims(&auth_token)["sub"]
+    );
+    assert_eq!(get_token_claims(&new_auth_token)["email"], new_email);
+
+    let response = app.post_confirm_email_change(&confirm_token).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn opening_link_should_not_change_anything() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, auth_token) = signup_and_login(&app).await;
+    let new_email = get_random_email();
+
+    let (confirm_token, undo_token) = email_change_tokens(&auth_token, &random_email, &new_email);
+
+    // Link scanners only ever GET the link, which returns the page with the button
+    let response = app.get_confirm_email_change(&confirm_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .headers()
+        .get("content-type")
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html")));
+
+    assert_eq!(login(&app, &random_email).await.status().as_u16(), 200);
+
+    let response = app.post_confirm_email_change(&confirm_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The undo token was not used up by the page opening either
+    let response = app.post_undo_email_change(&undo_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn undo_should_cancel_unconfirmed_change() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, auth_token) = signup_and_login(&app).await;
+    let new_email = get_random_email();
+
+    let (confirm_token, undo_token) = email_change_tokens(&auth_token, &random_email, &new_email);
+
+    let response = app.post_undo_email_change(&undo_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_confirm_email_change(&confirm_token).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(login(&app, &random_email).await.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn undo_should_revert_confirmed_change() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, old_auth_token) = signup_and_login(&app).await;
+    let new_email = get_random_email();
+
+    let (confirm_token, undo_token) =
+        email_change_tokens(&old_auth_token, &random_email, &new_email);
+
+    let response = app.post_confirm_email_change(&confirm_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &new_email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = app.post_undo_email_change(&undo_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(login(&app, &new_email).await.status().as_u16(), 401);
+    assert_eq!(login(&app, &random_email).await.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn undo_should_not_move_another_account_at_the_new_address() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, auth_token) = signup_and_login(&app).await;
+    let other_email = signup(&app).await;
+
+    // The change to other_email was never confirmed, so its account is not ours
+    let (_, undo_token) = email_change_tokens(&auth_token, &random_email, &other_email);
+
+    let response = app.post_undo_email_change(&undo_token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(login(&app, &other_email).await.status().as_u16(), 200);
+    assert_eq!(login(&app, &random_email).await.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,241 @@
+use auth_service::utils::constants::{
+    JWT_COOKIE_NAME, LOGIN_LOCKOUT_THRESHOLD, REFRESH_TOKEN_COOKIE_NAME,
+};
+use reqwest::Url;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn login(app: &TestApp, email: &str, password: &str) -> (String, String) {
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": password,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    (auth_token, refresh_token)
+}
+
+async fn signup(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    random_email
+}
+
+fn set_cookie(app: &TestApp, name: &str, value: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; HttpOnly; SameSite=Lax; Secure; Path=/", name, value),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "password123",
+            "newPassword": "newpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+    login(&app, &random_email, "password123").await;
+
+    let response = app
+        .post_change_password(&json!({
+            "newPassword": "newpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_new_password() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+    login(&app, &random_email, "password123").await;
+
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "password123",
+            "newPassword": "short"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_current_password() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+    login(&app, &random_email, "password123").await;
+
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "wrongpassword123",
+            "newPassword": "newpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    login(&app, &random_email, "password123").await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_423_once_wrong_passwords_lock_the_account() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+    login(&app, &random_email, "password123").await;
+
+    for _ in 1..*LOGIN_LOCKOUT_THRESHOLD {
+        let response = app
+            .post_change_password(&json!({
+                "currentPassword": "wrongpassword123",
+                "newPassword": "newpassword123"
+            }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "wrongpassword123",
+            "newPassword": "newpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 423);
+
+    // The right password does not help while the lock lasts, here or on /login
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "password123",
+            "newPassword": "newpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 423);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 423);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_change_password_and_end_other_sessions() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+    let (other_auth_token, other_refresh_token) = login(&app, &random_email, "password123").await;
+    let (auth_token, refresh_token) = login(&app, &random_email, "password123").await;
+
+    let response = app
+        .post_change_password(&json!({
+            "currentPassword": "password123",
+            "newPassword": "newpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The other session is logged out
+    let response = app
+        .post_verify_token(&json!({
+            "token": other_auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    set_cookie(&app, REFRESH_TOKEN_COOKIE_NAME, &other_refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // This one is not
+    let response = app
+        .post_verify_token(&json!({
+            "token": auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_cookie(&app, REFRESH_TOKEN_COOKIE_NAME, &refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    login(&app, &random_email, "newpassword123").await;
+
+    app.clean_up().await;
+}
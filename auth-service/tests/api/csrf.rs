@@ -0,0 +1,68 @@
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_403_if_cookie_request_from_untrusted_origin() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let response = app.post_logout_from_origin("https://evil.example").await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    // The session is untouched, so a legitimate logout still works
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_allow_cookie_request_from_configured_origin() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let response = app.post_logout_from_origin("http://localhost:8000").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_check_origin_without_auth_cookie() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_logout_from_origin("https://evil.example").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
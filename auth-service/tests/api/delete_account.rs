@@ -0,0 +1,219 @@
+use auth_service::{
+    domain::{data_stores::LoginAttemptId, Email},
+    routes::TwoFactorAuthResponse,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> (String, String, String) {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    (random_email, auth_token, refresh_token)
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_delete_account(&json!({
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_password() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, _, _) = signup_and_login(&app).await;
+
+    let response = app
+        .post_delete_account(&json!({
+            "password": "wrongpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_delete_account_and_revoke_everything() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, auth_token, refresh_token) = signup_and_login(&app).await;
+
+    let response = app
+        .post_delete_account(&json!({
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(auth_cookie.value().is_empty());
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, refresh_token
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_remove_pending_2fa_code() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": true
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let email = Email::parse(Secret::new(random_email.clone())).unwrap();
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let (_, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(Secret::new(login_attempt_id.clone())).unwrap())
+        .await
+        .expect("No 2FA code stored");
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret()
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Another login is left waiting on its code
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let response = app
+        .post_delete_account(&json!({
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let result = app.two_fa_code_store.read().await.get_codes(&email).await;
+
+    assert_eq!(result.map(|codes| codes.is_empty()), Ok(true));
+
+    app.clean_up().await;
+}
@@ -2,18 +2,28 @@ use std::{str::FromStr, sync::Arc};
 
 use auth_service::{
     app_state::AppState,
-    domain::data_stores::{BannedTokenStore, TwoFACodeStore},
+    domain::{
+        data_stores::{BannedTokenStore, RefreshTokenStore, SessionStore, TwoFACodeStore},
+        Email, EmailClient,
+    },
     get_postgres_pool, get_redis_client,
     services::{
         mock_email_client::MockEmailClient, postgres_user_store::PostgresUserStore,
         redis_banned_token_store::RedisBannedTokenStore,
+        redis_login_attempt_store::RedisLoginAttemptStore,
+        redis_refresh_token_store::RedisRefreshTokenStore, redis_session_store::RedisSessionStore,
         redis_two_fa_code_store::RedisTwoFACodeStore,
     },
     utils::constants::{test, DATABASE_URL, REDIS_HOST_NAME},
     Application,
 };
 
-use reqwest::cookie::Jar;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{eyre, Result};
+use reqwest::{
+    cookie::Jar,
+    header::{HeaderMap, HeaderValue, ORIGIN},
+};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
@@ -22,11 +32,23 @@ use sqlx::{
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+// Stands in for the email service being down
+pub struct FailingEmailClient;
+
+#[async_trait::async_trait]
+impl EmailClient for FailingEmailClient {
+    async fn send_email(&self, _recipient: &Email, _subject: &str, _content: &str) -> Result<()> {
+        Err(eyre!("email service unavailable"))
+    }
+}
+
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
     pub http_client: reqwest::Client,
     pub banned_token_store: Arc<RwLock<dyn BannedTokenStore>>,
+    pub refresh_token_store: Arc<RwLock<dyn RefreshTokenStore>>,
+    pub session_store: Arc<RwLock<dyn SessionStore>>,
     pub two_fa_code_store: Arc<RwLock<dyn TwoFACodeStore>>,
     pub db_name: String,
     pub clean_up_called: bool,
@@ -34,6 +56,10 @@ pub struct TestApp {
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::with_email_client(Arc::new(MockEmailClient)).await
+    }
+
+    pub async fn with_email_client(email_client: Arc<dyn EmailClient + Send + Sync>) -> Self {
         // We are creating a new database for each test case, and we need to ensure
         // each database has a unique name!
         let db_name = Uuid::new_v4().to_string();
@@ -44,13 +70,19 @@ impl TestApp {
         let user_store = PostgresUserStore::new(pg_pool);
         let banned_token_store =
             Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
+        let refresh_token_store =
+            Arc::new(RwLock::new(RedisRefreshTokenStore::new(redis_conn.clone())));
+        let session_store = Arc::new(RwLock::new(RedisSessionStore::new(redis_conn.clone())));
         let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn.clone())));
-        let email_client = Arc::new(MockEmailClient);
+        let login_attempt_store = RedisLoginAttemptStore::new(redis_conn.clone());
         let app_state = AppState {
             user_store: Arc::new(RwLock::new(user_store)),
             banned_token_store: banned_token_store.clone(),
+            refresh_token_store: refresh_token_store.clone(),
+            session_store: session_store.clone(),
             two_fa_code_store: two_fa_code_store.clone(),
-            email_client: email_client,
+            login_attempt_store: Arc::new(RwLock::new(login_attempt_store)),
+            email_client,
         };
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
@@ -63,9 +95,14 @@ impl TestApp {
         #[allow(clippy::let_underscore_future)]
         let _ = tokio::spawn(app.run());
 
+        // Behave like a browser on a page served by the auth service, so CSRF checks pass
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(ORIGIN, HeaderValue::from_str(&address).unwrap());
+
         let cookie_jar = Arc::new(Jar::default());
         let http_client = reqwest::Client::builder()
             .cookie_provider(cookie_jar.clone())
+            .default_headers(default_headers)
             .build()
             .unwrap();
 
@@ -73,8 +110,10 @@ impl TestApp {
             address,
             cookie_jar,
             http_client,
-            banned_token_store: banned_token_store,
-            two_fa_code_store: two_fa_code_store,
+            banned_token_store,
+            refresh_token_store,
+            session_store,
+            two_fa_code_store,
             db_name,
             clean_up_called: false,
         }
@@ -82,7 +121,15 @@ impl TestApp {
 
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -93,7 +140,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -105,7 +152,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .json(body)
             .send()
             .await
@@ -114,7 +161,33 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/logout", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_from_origin(&self, origin: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout", &self.address))
+            .header(ORIGIN, origin)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout-all", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -125,7 +198,31 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/verify-2fa", &self.address))
+            .post(format!("{}/verify-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_verification<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-verification", &self.address))
             .json(body)
             .send()
             .await
@@ -137,13 +234,276 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/verify-token", &self.address))
+            .post(format!("{}/verify-token", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_introspect<Body>(
+        &self,
+        body: &Body,
+        credentials: Option<(&str, &str)>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/introspect", &self.address))
+            .form(body);
+
+        if let Some((client_id, secret)) = credentials {
+            request = request.basic_auth(client_id, Some(secret));
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/verify-email", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_email(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/verify-email", &self.address))
+            .json(&serde_json::json!({ "token": token }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_forgot_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/forgot-password", &self.address))
             .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_reset_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/reset-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_2fa_settings<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/settings/2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_enroll_totp<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/settings/2fa/totp", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_confirm_totp<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/settings/2fa/totp/confirm", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_recovery_codes(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/settings/2fa/recovery-codes", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_recovery_codes<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/settings/2fa/recovery-codes", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_delete_account<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/delete-account", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-email", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_confirm_email_change(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/confirm-email-change", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_confirm_email_change(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/confirm-email-change", &self.address))
+            .json(&serde_json::json!({ "token": token }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_undo_email_change(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/undo-email-change", &self.address))
+            .json(&serde_json::json!({ "token": token }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_me(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/me", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn patch_me<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .patch(format!("{}/me", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_unlock_account(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/unlock-account", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_unlock_account(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/unlock-account", &self.address))
+            .json(&serde_json::json!({ "token": token }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_user_status<Body>(
+        &self,
+        body: &Body,
+        credentials: Option<(&str, &str)>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/admin/users/status", &self.address))
+            .json(body);
+
+        if let Some((client_id, secret)) = credentials {
+            request = request.basic_auth(client_id, Some(secret));
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn clean_up(&mut self) {
         delete_database(&self.db_name).await;
         self.clean_up_called = true;
@@ -175,7 +535,7 @@ async fn configure_postgresql(db_name: String) -> PgPool {
 async fn configure_database(db_conn_string: &Secret<String>, db_name: &str) {
     // Create database connection
     let connection = PgPoolOptions::new()
-        .connect(&db_conn_string.expose_secret())
+        .connect(db_conn_string.expose_secret())
         .await
         .expect("Failed to create Postgres connection pool.");
 
@@ -203,7 +563,7 @@ async fn configure_database(db_conn_string: &Secret<String>, db_name: &str) {
 async fn delete_database(db_name: &str) {
     let postgresql_conn_url = DATABASE_URL.expose_secret();
 
-    let connection_options = PgConnectOptions::from_str(&postgresql_conn_url)
+    let connection_options = PgConnectOptions::from_str(postgresql_conn_url)
         .expect("Failed to parse PostgreSQL connection string!");
 
     let mut connection = PgConnection::connect_with(&connection_options)
@@ -234,6 +594,15 @@ async fn delete_database(db_name: &str) {
         .expect("Failed to drop the database");
 }
 
+// Reads the claims of a JWT without verifying it
+pub fn get_token_claims(token: &str) -> serde_json::Value {
+    let payload = token.split('.').nth(1).expect("Token has no payload");
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .expect("Failed to decode token payload");
+    serde_json::from_slice(&payload).expect("Failed to parse token claims")
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
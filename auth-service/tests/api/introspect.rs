@@ -0,0 +1,188 @@
+use auth_service::{
+    routes::{IntrospectionResponse, MeResponse},
+    utils::constants::{INTROSPECTION_CLIENTS, JWT_COOKIE_NAME},
+};
+use secrecy::ExposeSecret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, get_token_claims, TestApp};
+
+fn client_credentials() -> (String, String) {
+    let (client_id, secret) = INTROSPECTION_CLIENTS
+        .iter()
+        .next()
+        .expect("INTROSPECTION_CLIENTS must be set to run these tests");
+
+    (client_id.to_owned(), secret.expose_secret().to_owned())
+}
+
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (random_email, token)
+}
+
+#[tokio::test]
+async fn should_return_401_if_client_not_authenticated() {
+    let mut app = TestApp::new().await;
+
+    let (client_id, _) = client_credentials();
+
+    let test_cases = [None, Some((client_id.as_str(), "wrong-secret"))];
+
+    for credentials in test_cases {
+        let response = app
+            .post_introspect(&[("token", "abc123")], credentials)
+            .await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_inactive_for_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let (client_id, secret) = client_credentials();
+
+    let response = app
+        .post_introspect(&[("token", "abc123")], Some((&client_id, &secret)))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .expect("Could not deserialize response body");
+
+    assert_eq!(body, json!({ "active": false }));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_token_details_for_active_token() {
+    let mut app = TestApp::new().await;
+
+    let (client_id, secret) = client_credentials();
+    let (email, token) = signup_and_login(&app).await;
+
+    let response = app
+        .post_introspect(&[("token", token.as_str())], Some((&client_id, &secret)))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body");
+
+    let me = app.get_me().await.json::<MeResponse>().await.unwrap();
+
+    assert!(body.active);
+    assert_eq!(body.sub, Some(me.id));
+    assert_eq!(body.username, Some(email));
+    assert_eq!(body.scope.as_deref(), Some(""));
+    assert_eq!(body.client_id.as_deref(), Some("app-service"));
+    assert!(body.exp > body.iat);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_inactive_after_logout() {
+    let mut app = TestApp::new().await;
+
+    let (client_id, secret) = client_credentials();
+    let (_, token) = signup_and_login(&app).await;
+
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_introspect(&[("token", token.as_str())], Some((&client_id, &secret)))
+        .await;
+
+    let body = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body");
+
+    assert!(!body.active);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_keep_session_alive() {
+    let mut app = TestApp::new().await;
+
+    let (client_id, secret) = client_credentials();
+    let (_, token) = signup_and_login(&app).await;
+
+    let session_id = get_token_claims(&token)["sid"]
+        .as_str()
+        .expect("No session id in token")
+        .to_owned();
+
+    app.session_store
+        .write()
+        .await
+        .touch_session(&session_id, 0)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_introspect(&[("token", token.as_str())], Some((&client_id, &secret)))
+        .await;
+
+    let body = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body");
+
+    assert!(body.active);
+
+    let session = app
+        .session_store
+        .read()
+        .await
+        .get_session(&session_id)
+        .await
+        .unwrap();
+
+    assert_eq!(session.last_seen_at, 0);
+
+    app.clean_up().await;
+}
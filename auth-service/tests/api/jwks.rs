@@ -0,0 +1,30 @@
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::helpers::TestApp;
+
+#[tokio::test]
+async fn jwks_returns_public_key_set() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_jwks().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/json"
+    );
+
+    let body = response
+        .json::<serde_json::Value>()
+        .await
+        .expect("Could not deserialize response body to JSON");
+
+    // Private key material must never be published
+    for key in body["keys"].as_array().expect("keys should be an array") {
+        assert!(key.get("d").is_none());
+    }
+
+    serde_json::from_value::<JwkSet>(body).expect("Could not deserialize response body to JwkSet");
+
+    app.clean_up().await;
+}
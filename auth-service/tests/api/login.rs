@@ -1,7 +1,9 @@
 use auth_service::{
-    domain::Email, routes::TwoFactorAuthResponse, utils::constants::JWT_COOKIE_NAME,
+    domain::{data_stores::LoginAttemptId, Email},
+    routes::TwoFactorAuthResponse,
+    utils::constants::JWT_COOKIE_NAME,
 };
-use secrecy::{ExposeSecret, Secret};
+use secrecy::Secret;
 use serde_json::json;
 
 use crate::helpers::{get_random_email, TestApp};
@@ -72,6 +74,35 @@ async fn should_return_400_if_invalid_input() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_400_if_unknown_audience() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(signup_response.status().as_u16(), 201);
+
+    let login_response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+            "audience": "unknown-service"
+        }))
+        .await;
+
+    assert_eq!(login_response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_401_if_incorrect_credentials() {
     let mut app = TestApp::new().await;
@@ -169,15 +200,17 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     assert_eq!(json_body.message, "2FA required".to_owned());
 
     let email = Email::parse(Secret::new(random_email)).unwrap();
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(json_body.login_attempt_id)).unwrap();
 
-    let result = app.two_fa_code_store.read().await.get_code(&email).await;
+    let (challenge_email, _) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&login_attempt_id)
+        .await
+        .expect("No 2FA code stored for the login attempt");
 
-    if let Ok((login_attempt_id, _)) = result {
-        assert_eq!(
-            json_body.login_attempt_id,
-            login_attempt_id.as_ref().expose_secret().to_owned()
-        );
-    }
+    assert_eq!(challenge_email, email);
 
     app.clean_up().await;
 }
@@ -1,8 +1,7 @@
 use auth_service::utils::constants::JWT_COOKIE_NAME;
 use reqwest::Url;
-use secrecy::Secret;
 
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{get_random_email, get_token_claims, TestApp};
 
 #[tokio::test]
 async fn should_return_400_if_jwt_cookie_missing() {
@@ -67,13 +66,32 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert!(!auth_cookie.value().is_empty());
 
+    assert_eq!(
+        auth_cookie.max_age(),
+        Some(std::time::Duration::from_secs(600))
+    );
+
     let response = app.post_logout().await;
 
     assert_eq!(response.status().as_u16(), 200);
 
+    // The removal cookie must match the original path for browsers to drop it
+    let removal_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No removal cookie found");
+
+    assert!(removal_cookie.value().is_empty());
+    assert_eq!(removal_cookie.path(), auth_cookie.path());
+    assert_eq!(removal_cookie.max_age(), Some(std::time::Duration::ZERO));
+
     let banned_token_store = app.banned_token_store.read().await;
     let result = banned_token_store
-        .contains_token(Secret::new(auth_cookie.value().to_string()))
+        .contains_token(
+            get_token_claims(auth_cookie.value())["jti"]
+                .as_str()
+                .expect("No jti claim found"),
+        )
         .await;
 
     assert_eq!(result, Ok(true));
@@ -125,3 +143,52 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_return_200_if_valid_bearer_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    // Drop the cookie so only the header is sent
+    app.cookie_jar.add_cookie_str(
+        &format!("{}=; Max-Age=0; Path=/", JWT_COOKIE_NAME),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+
+    let response = app.post_logout_with_bearer(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_logout_with_bearer(&token).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
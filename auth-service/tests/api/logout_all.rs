@@ -0,0 +1,117 @@
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use reqwest::Url;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn login(app: &TestApp, email: &str) -> (String, String) {
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    (auth_token, refresh_token)
+}
+
+fn set_cookie(app: &TestApp, name: &str, value: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; HttpOnly; SameSite=Lax; Secure; Path=/", name, value),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    set_cookie(&app, JWT_COOKIE_NAME, "invalid");
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_tokens_from_every_login() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Log in from "another device" first, then from this one, possibly
+    // in the same second, whose tokens must not survive either
+    let (other_auth_token, other_refresh_token) = login(&app, &random_email).await;
+    login(&app, &random_email).await;
+
+    let response = app.post_logout_all().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": other_auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    set_cookie(&app, REFRESH_TOKEN_COOKIE_NAME, &other_refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Logging in again right away still works, since the login waits out
+    // the second the tokens were revoked in
+    let (auth_token, _) = login(&app, &random_email).await;
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
@@ -1,7 +1,27 @@
+mod change_email;
+mod change_password;
+mod csrf;
+mod delete_account;
+mod forgot_password;
 mod helpers;
+mod introspect;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod me;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
+mod resend_verification;
+mod reset_password;
 mod root;
+mod sessions;
 mod signup;
+mod totp;
+mod two_fa_settings;
+mod unlock_account;
+mod user_status;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
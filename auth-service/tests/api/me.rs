@@ -0,0 +1,128 @@
+use auth_service::routes::MeResponse;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    random_email
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_me().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.patch_me(&json!({ "displayName": "Jane" })).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_logged_in_user() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup_and_login(&app).await;
+
+    let response = app.get_me().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let me = response
+        .json::<MeResponse>()
+        .await
+        .expect("Could not deserialize response body to MeResponse");
+
+    assert_eq!(me.email, random_email);
+    assert_eq!(me.display_name, None);
+    assert!(!me.requires_2fa);
+    assert!(!me.email_verified);
+    assert!(me.last_login_at.is_some_and(|at| at >= me.created_at));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_update_and_clear_display_name() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let response = app.patch_me(&json!({ "displayName": "  Jane Doe " })).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let me = response
+        .json::<MeResponse>()
+        .await
+        .expect("Could not deserialize response body to MeResponse");
+
+    assert_eq!(me.display_name, Some("Jane Doe".to_owned()));
+
+    // Leaving the field out keeps it
+    let response = app.patch_me(&json!({})).await;
+    let me = response.json::<MeResponse>().await.unwrap();
+
+    assert_eq!(me.display_name, Some("Jane Doe".to_owned()));
+
+    let response = app.patch_me(&json!({ "displayName": null })).await;
+    let me = response.json::<MeResponse>().await.unwrap();
+
+    assert_eq!(me.display_name, None);
+
+    let me = app.get_me().await.json::<MeResponse>().await.unwrap();
+
+    assert_eq!(me.display_name, None);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_display_name() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let test_cases = [
+        json!({ "displayName": "" }),
+        json!({ "displayName": "a".repeat(65) }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.patch_me(test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    app.clean_up().await;
+}
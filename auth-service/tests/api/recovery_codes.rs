@@ -0,0 +1,191 @@
+use auth_service::routes::{
+    RecoveryCodesCountResponse, RecoveryCodesResponse, SignupResponse, TwoFactorAuthResponse,
+};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_with_2fa(app: &TestApp) -> (String, Vec<String>) {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": true
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let recovery_codes = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse")
+        .recovery_codes
+        .expect("No recovery codes issued");
+
+    (random_email, recovery_codes)
+}
+
+async fn verify_with_recovery_code(app: &TestApp, email: &str, code: &str) -> reqwest::Response {
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    app.post_verify_2fa(&json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": code
+    }))
+    .await
+}
+
+async fn remaining(app: &TestApp) -> usize {
+    let response = app.get_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<RecoveryCodesCountResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesCountResponse")
+        .remaining
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_recovery_codes().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_login_with_recovery_code_once() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, recovery_codes) = signup_with_2fa(&app).await;
+
+    assert_eq!(recovery_codes.len(), 10);
+
+    // Codes are accepted however the user types them
+    let code = recovery_codes[0].to_uppercase().replace('-', " ");
+
+    let response = verify_with_recovery_code(&app, &random_email, &code).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(remaining(&app).await, 9);
+
+    let response = verify_with_recovery_code(&app, &random_email, &recovery_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_recovery_code_unknown() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, _) = signup_with_2fa(&app).await;
+
+    let response = verify_with_recovery_code(&app, &random_email, "abcde-fghjk").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_regenerating_with_incorrect_password() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, recovery_codes) = signup_with_2fa(&app).await;
+    verify_with_recovery_code(&app, &random_email, &recovery_codes[0]).await;
+
+    let response = app
+        .post_recovery_codes(&json!({
+            "password": "wrongpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_replace_recovery_codes_when_regenerating() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, old_codes) = signup_with_2fa(&app).await;
+    verify_with_recovery_code(&app, &random_email, &old_codes[0]).await;
+
+    let response = app
+        .post_recovery_codes(&json!({
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let new_codes = response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Could not deserialize response body to RecoveryCodesResponse")
+        .recovery_codes;
+
+    assert_eq!(new_codes.len(), 10);
+    assert_eq!(remaining(&app).await, 10);
+
+    let response = verify_with_recovery_code(&app, &random_email, &old_codes[1]).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = verify_with_recovery_code(&app, &random_email, &new_codes[0]).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_remove_recovery_codes_when_disabling_2fa() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, recovery_codes) = signup_with_2fa(&app).await;
+    verify_with_recovery_code(&app, &random_email, &recovery_codes[0]).await;
+
+    let response = app
+        .post_2fa_settings(&json!({
+            "enabled": false,
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(remaining(&app).await, 0);
+
+    // Without 2FA there is nothing to regenerate
+    let response = app
+        .post_recovery_codes(&json!({
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,226 @@
+use auth_service::{
+    domain::data_stores::RefreshToken,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
+use reqwest::Url;
+use secrecy::Secret;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> reqwest::Response {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+}
+
+fn set_refresh_cookie(app: &TestApp, value: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!(
+            "{}={}; HttpOnly; SameSite=Lax; Secure; Path=/",
+            REFRESH_TOKEN_COOKIE_NAME, value
+        ),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_refresh_token() {
+    let mut app = TestApp::new().await;
+
+    set_refresh_cookie(&app, "invalid");
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_rotate_refresh_token() {
+    let mut app = TestApp::new().await;
+
+    let response = signup_and_login(&app).await;
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found");
+
+    assert!(!refresh_cookie.value().is_empty());
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+
+    let rotated_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found");
+
+    assert_ne!(rotated_cookie.value(), refresh_cookie.value());
+
+    let refresh_token_store = app.refresh_token_store.read().await;
+    let record = refresh_token_store
+        .get_token(
+            &RefreshToken::parse(Secret::new(refresh_cookie.value().to_owned()))
+                .expect("Failed to parse refresh token"),
+        )
+        .await
+        .expect("Refresh token not found");
+
+    assert!(record.used);
+
+    drop(refresh_token_store);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_token_family_if_refresh_token_reused() {
+    let mut app = TestApp::new().await;
+
+    let response = signup_and_login(&app).await;
+
+    let original_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found");
+    let original_token = original_cookie.value().to_owned();
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let rotated_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    // Replay the token that has already been exchanged
+    set_refresh_cookie(&app, &original_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The legitimately rotated token belongs to the same family and is revoked too
+    set_refresh_cookie(&app, &rotated_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_end_session_if_refresh_token_reused() {
+    let mut app = TestApp::new().await;
+
+    let response = signup_and_login(&app).await;
+
+    let original_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    set_refresh_cookie(&app, &original_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The access token from the last rotation belongs to the ended session
+    let response = app
+        .post_verify_token(&serde_json::json!({
+            "token": auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_after_logout() {
+    let mut app = TestApp::new().await;
+
+    let response = signup_and_login(&app).await;
+
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    set_refresh_cookie(&app, &refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,139 @@
+use auth_service::routes::TwoFactorAuthResponse;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_start_login(app: &TestApp) -> (String, String) {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": true
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    (random_email, login_attempt_id)
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let test_cases = [
+        json!({}),
+        json!({
+            "email": random_email
+        }),
+        json!({
+            "loginAttemptId": "d5783dff-c1b4-4ae3-81c6-cb71674a0d1e"
+        }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_resend_2fa(&test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, _) = signup_and_start_login(&app).await;
+
+    let response = app
+        .post_resend_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": "not-a-uuid"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_login_attempt_unknown() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, _) = signup_and_start_login(&app).await;
+
+    let response = app
+        .post_resend_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_resent_during_cooldown() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, login_attempt_id) = signup_and_start_login(&app).await;
+
+    // The login itself just sent the code
+    let response = app
+        .post_resend_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_login_attempt_belongs_to_another_user() {
+    let mut app = TestApp::new().await;
+
+    let (_, login_attempt_id) = signup_and_start_login(&app).await;
+    let (other_email, _) = signup_and_start_login(&app).await;
+
+    let response = app
+        .post_resend_2fa(&json!({
+            "email": other_email,
+            "loginAttemptId": login_attempt_id
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,75 @@
+use auth_service::routes::ResendVerificationResponse;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_resend_verification(&json!({})).await;
+
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_email() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_resend_verification(&json!({
+            "email": "not-an-email"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_respond_the_same_whether_or_not_user_exists() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let existing = app
+        .post_resend_verification(&json!({
+            "email": random_email
+        }))
+        .await;
+
+    let missing = app
+        .post_resend_verification(&json!({
+            "email": get_random_email()
+        }))
+        .await;
+
+    assert_eq!(existing.status().as_u16(), 200);
+    assert_eq!(missing.status().as_u16(), 200);
+
+    assert_eq!(
+        existing
+            .json::<ResendVerificationResponse>()
+            .await
+            .expect("Could not deserialize response body to ResendVerificationResponse"),
+        missing
+            .json::<ResendVerificationResponse>()
+            .await
+            .expect("Could not deserialize response body to ResendVerificationResponse"),
+    );
+
+    app.clean_up().await;
+}
@@ -0,0 +1,175 @@
+use auth_service::{
+    domain::Email,
+    utils::{
+        constants::JWT_COOKIE_NAME,
+        email_token::{generate_email_token, EmailTokenPurpose},
+    },
+};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (random_email, auth_token)
+}
+
+fn reset_token(email: &str, purpose: EmailTokenPurpose) -> String {
+    let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+    generate_email_token(&email, purpose)
+        .unwrap()
+        .expose_secret()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_reset_password(&json!({
+            "token": "token"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_password() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, _) = signup_and_login(&app).await;
+    let token = reset_token(&random_email, EmailTokenPurpose::ResetPassword);
+
+    let response = app
+        .post_reset_password(&json!({
+            "token": token,
+            "newPassword": "short"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    // The token was not used up by the rejected request
+    let response = app
+        .post_reset_password(&json!({
+            "token": token,
+            "newPassword": "newpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, _) = signup_and_login(&app).await;
+
+    let test_cases = [
+        "invalid".to_owned(),
+        reset_token(&random_email, EmailTokenPurpose::VerifyEmail),
+    ];
+
+    for token in test_cases {
+        let response = app
+            .post_reset_password(&json!({
+                "token": token,
+                "newPassword": "newpassword123"
+            }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_password_and_revoke_tokens() {
+    let mut app = TestApp::new().await;
+
+    let (random_email, auth_token) = signup_and_login(&app).await;
+    let token = reset_token(&random_email, EmailTokenPurpose::ResetPassword);
+
+    let response = app
+        .post_reset_password(&json!({
+            "token": token,
+            "newPassword": "newpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "newpassword123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The token only works once
+    let response = app
+        .post_reset_password(&json!({
+            "token": token,
+            "newPassword": "otherpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,246 @@
+use auth_service::{
+    domain::Email,
+    routes::SessionResponse,
+    utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
+use reqwest::Url;
+use secrecy::Secret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, get_token_claims, TestApp};
+
+async fn login(app: &TestApp, email: &str, device: &str) -> (String, String) {
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": "password123",
+            "device": device,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let refresh_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found")
+        .value()
+        .to_owned();
+
+    (auth_token, refresh_token)
+}
+
+async fn signup(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    random_email
+}
+
+fn set_cookie(app: &TestApp, name: &str, value: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; HttpOnly; SameSite=Lax; Secure; Path=/", name, value),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn login_should_record_session() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+    let (auth_token, _) = login(&app, &random_email, "laptop").await;
+
+    let sid = get_token_claims(&auth_token)["sid"]
+        .as_str()
+        .expect("No sid claim")
+        .to_owned();
+
+    let session = app
+        .session_store
+        .read()
+        .await
+        .get_session(&sid)
+        .await
+        .expect("Session was not recorded");
+
+    assert_eq!(
+        session.email,
+        Email::parse(Secret::new(random_email)).unwrap()
+    );
+    assert_eq!(session.device, Some("laptop".to_owned()));
+    assert_eq!(session.ip, Some("127.0.0.1".to_owned()));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_sessions_and_mark_current() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+    login(&app, &random_email, "phone").await;
+    let (auth_token, _) = login(&app, &random_email, "laptop").await;
+
+    let response = app.get_sessions().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sessions = response
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<SessionResponse>");
+
+    assert_eq!(sessions.len(), 2);
+
+    let current = sessions
+        .iter()
+        .find(|session| session.current)
+        .expect("No current session");
+
+    assert_eq!(current.id, get_token_claims(&auth_token)["sid"]);
+    assert_eq!(current.device, Some("laptop".to_owned()));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_other_session() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+    let (other_auth_token, other_refresh_token) = login(&app, &random_email, "phone").await;
+    let (auth_token, refresh_token) = login(&app, &random_email, "laptop").await;
+
+    let other_sid = get_token_claims(&other_auth_token)["sid"]
+        .as_str()
+        .expect("No sid claim")
+        .to_owned();
+
+    let response = app.delete_session(&other_sid).await;
+
+    assert_eq!(response.status().as_u16(), 204);
+
+    // The revoked session's tokens stop working
+    let response = app
+        .post_verify_token(&json!({
+            "token": other_auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    set_cookie(&app, REFRESH_TOKEN_COOKIE_NAME, &other_refresh_token);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // This session is untouched
+    set_cookie(&app, REFRESH_TOKEN_COOKIE_NAME, &refresh_token);
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_refresh().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_another_users_session() {
+    let mut app = TestApp::new().await;
+
+    let other_email = signup(&app).await;
+    let (other_auth_token, _) = login(&app, &other_email, "phone").await;
+
+    let random_email = signup(&app).await;
+    login(&app, &random_email, "laptop").await;
+
+    let other_sid = get_token_claims(&other_auth_token)["sid"]
+        .as_str()
+        .expect("No sid claim")
+        .to_owned();
+
+    let response = app.delete_session(&other_sid).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": other_auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn revoking_current_session_should_remove_cookies() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+    let (auth_token, _) = login(&app, &random_email, "laptop").await;
+
+    let sid = get_token_claims(&auth_token)["sid"]
+        .as_str()
+        .expect("No sid claim")
+        .to_owned();
+
+    let response = app.delete_session(&sid).await;
+
+    assert_eq!(response.status().as_u16(), 204);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(auth_cookie.value().is_empty());
+
+    let response = app
+        .post_verify_token(&json!({
+            "token": auth_token
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
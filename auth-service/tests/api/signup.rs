@@ -1,6 +1,8 @@
+use std::sync::Arc;
+
 use serde_json::json;
 
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{get_random_email, FailingEmailClient, TestApp};
 
 use auth_service::{routes::SignupResponse, ErrorResponse};
 
@@ -63,17 +65,52 @@ async fn should_return_201_if_valid_input() {
 
     assert_eq!(response.status().as_u16(), 201);
 
-    let expected_response = SignupResponse {
-        message: "User created successfully!".to_owned(),
-    };
+    let body = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse");
 
-    assert_eq!(
-        response
-            .json::<SignupResponse>()
-            .await
-            .expect("Could not deserialize response body to SignupResponse"),
-        expected_response
-    );
+    assert_eq!(body.message, "User created successfully!");
+    // Signing up with 2FA issues recovery codes straight away
+    assert_eq!(body.recovery_codes.map(|codes| codes.len()), Some(10));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_account_if_verification_email_fails() {
+    let mut app = TestApp::with_email_client(Arc::new(FailingEmailClient)).await;
+
+    let random_email = get_random_email();
+    let user = json!({
+        "email": random_email,
+        "password": "test123456",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&user).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    // The recovery codes are only ever shown here, so they must not be lost
+    let body = response
+        .json::<SignupResponse>()
+        .await
+        .expect("Could not deserialize response body to SignupResponse");
+
+    assert_eq!(body.recovery_codes.map(|codes| codes.len()), Some(10));
+
+    let response = app.post_signup(&user).await;
+
+    assert_eq!(response.status().as_u16(), 409);
+
+    let response = app
+        .post_resend_verification(&json!({
+            "email": random_email
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
 
     app.clean_up().await;
 }
@@ -0,0 +1,401 @@
+use auth_service::{
+    domain::{data_stores::LoginAttemptId, TotpSecret},
+    routes::{ConfirmTotpResponse, EnrollTotpResponse, TwoFactorAuthResponse},
+    utils::{auth::now_timestamp, constants::LOGIN_LOCKOUT_THRESHOLD},
+};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup_and_login(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    random_email
+}
+
+async fn enroll(app: &TestApp) -> TotpSecret {
+    let response = app
+        .post_enroll_totp(&json!({
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<EnrollTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to EnrollTotpResponse");
+
+    assert!(body.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(body
+        .otpauth_uri
+        .contains(&format!("secret={}", body.secret)));
+
+    TotpSecret::parse(Secret::new(body.secret)).expect("Invalid TOTP secret")
+}
+
+// Confirming uses up the current time step, so later codes come from the next one
+fn next_code(secret: &TotpSecret) -> String {
+    secret
+        .generate_code(now_timestamp() + 30)
+        .as_ref()
+        .expose_secret()
+        .to_owned()
+}
+
+async fn enroll_and_confirm(app: &TestApp) -> TotpSecret {
+    let secret = enroll(app).await;
+    let code = secret.generate_code(now_timestamp());
+
+    let response = app
+        .post_confirm_totp(&json!({
+            "2FACode": code.as_ref().expose_secret(),
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<ConfirmTotpResponse>()
+        .await
+        .expect("Could not deserialize response body to ConfirmTotpResponse");
+
+    assert!(body.requires_2fa);
+    assert_eq!(body.two_fa_method, "totp");
+    // Confirming the app is what turned 2FA on, so recovery codes come with it
+    assert_eq!(body.recovery_codes.map(|codes| codes.len()), Some(10));
+
+    secret
+}
+
+async fn start_totp_login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    assert_eq!(body.two_fa_method, "totp");
+
+    body.login_attempt_id
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_enroll_totp(&json!({
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_confirming_without_enrollment() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let response = app
+        .post_confirm_totp(&json!({
+            "2FACode": "123456",
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_confirming_with_incorrect_code() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup_and_login(&app).await;
+    let secret = enroll(&app).await;
+
+    let code = secret
+        .generate_code(now_timestamp() + 300)
+        .as_ref()
+        .expose_secret()
+        .to_owned();
+
+    let response = app
+        .post_confirm_totp(&json!({
+            "2FACode": code,
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The account keeps logging in without a second factor until confirmed
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_login_with_totp_code() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup_and_login(&app).await;
+    let secret = enroll_and_confirm(&app).await;
+
+    let login_attempt_id = start_totp_login(&app, &random_email).await;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": next_code(&secret)
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_totp_code_is_reused() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup_and_login(&app).await;
+    let secret = enroll_and_confirm(&app).await;
+    let code = next_code(&secret);
+
+    let login_attempt_id = start_totp_login(&app, &random_email).await;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_attempt_id = start_totp_login(&app, &random_email).await;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_accept_stored_email_code_for_totp_users() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup_and_login(&app).await;
+    enroll_and_confirm(&app).await;
+
+    let login_attempt_id = start_totp_login(&app, &random_email).await;
+
+    let (_, stored_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(Secret::new(login_attempt_id.clone())).unwrap())
+        .await
+        .expect("No 2FA code stored");
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": stored_code.as_ref().expose_secret()
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_disable_2fa_with_totp_code() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup_and_login(&app).await;
+    let secret = enroll_and_confirm(&app).await;
+
+    let response = app
+        .post_2fa_settings(&json!({
+            "enabled": false,
+            "2FACode": next_code(&secret)
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_enrolling_with_incorrect_password() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+
+    let response = app
+        .post_enroll_totp(&json!({
+            "password": "wrongpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_confirming_with_incorrect_password() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let secret = enroll(&app).await;
+    let code = secret.generate_code(now_timestamp());
+
+    let response = app
+        .post_confirm_totp(&json!({
+            "2FACode": code.as_ref().expose_secret(),
+            "password": "wrongpassword123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_current_code_to_replace_authenticator_app() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let secret = enroll_and_confirm(&app).await;
+
+    let response = app
+        .post_enroll_totp(&json!({
+            "password": "password123"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .post_enroll_totp(&json!({
+            "password": "password123",
+            "2FACode": next_code(&secret)
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_423_once_wrong_codes_lock_the_account() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app).await;
+    let secret = enroll_and_confirm(&app).await;
+
+    let code = next_code(&secret);
+    let wrong_code = if code == "123456" { "654321" } else { "123456" };
+
+    for _ in 1..*LOGIN_LOCKOUT_THRESHOLD {
+        let response = app
+            .post_2fa_settings(&json!({
+                "enabled": false,
+                "2FACode": wrong_code
+            }))
+            .await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_2fa_settings(&json!({
+            "enabled": false,
+            "2FACode": wrong_code
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 423);
+
+    // Not even the right code gets through while the lock lasts
+    let response = app
+        .post_2fa_settings(&json!({
+            "enabled": false,
+            "2FACode": code
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 423);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,227 @@
+use auth_service::{
+    domain::data_stores::LoginAttemptId,
+    routes::{TwoFASettingsResponse, TwoFactorAuthResponse},
+};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, requires_2fa: bool) -> String {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": requires_2fa
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    random_email
+}
+
+// Starts a login and returns the 2FA code it emailed
+async fn start_2fa_login(app: &TestApp, email: &str) -> (String, String) {
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let (_, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(Secret::new(login_attempt_id.clone())).unwrap())
+        .await
+        .expect("No 2FA code stored");
+
+    (
+        login_attempt_id,
+        two_fa_code.as_ref().expose_secret().to_owned(),
+    )
+}
+
+async fn login_with_2fa(app: &TestApp, email: &str) {
+    let (login_attempt_id, two_fa_code) = start_2fa_login(app, email).await;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn requires_2fa(response: reqwest::Response) -> bool {
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<TwoFASettingsResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFASettingsResponse")
+        .requires_2fa
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_2fa_settings(&json!({
+            "enabled": true
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_enable_2fa() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app, false).await;
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_2fa_settings(&json!({
+            "enabled": true
+        }))
+        .await;
+
+    assert!(requires_2fa(response).await);
+
+    start_2fa_login(&app, &random_email).await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_disabling_without_confirmation() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app, true).await;
+    login_with_2fa(&app, &random_email).await;
+
+    let response = app
+        .post_2fa_settings(&json!({
+            "enabled": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_disabling_with_incorrect_confirmation() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app, true).await;
+    login_with_2fa(&app, &random_email).await;
+
+    let test_cases = [json!({
+        "enabled": false,
+        "password": "wrongpassword123"
+    })];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_2fa_settings(test_case).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_disable_2fa_with_password() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app, true).await;
+    login_with_2fa(&app, &random_email).await;
+
+    let response = app
+        .post_2fa_settings(&json!({
+            "enabled": false,
+            "password": "password123"
+        }))
+        .await;
+
+    assert!(!requires_2fa(response).await);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_accept_pending_login_code() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app, true).await;
+    login_with_2fa(&app, &random_email).await;
+
+    let (login_attempt_id, two_fa_code) = start_2fa_login(&app, &random_email).await;
+
+    // Users on email codes confirm with their password instead
+    let response = app
+        .post_2fa_settings(&json!({
+            "enabled": false,
+            "2FACode": two_fa_code
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    // The login the code belongs to can still finish
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
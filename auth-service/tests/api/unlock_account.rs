@@ -0,0 +1,186 @@
+use auth_service::{
+    domain::Email,
+    utils::{
+        constants::LOGIN_LOCKOUT_THRESHOLD,
+        email_token::{generate_email_token, EmailTokenPurpose},
+    },
+};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    random_email
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+}
+
+// Fails `count` logins in a row, returning the status of the last one
+async fn fail_logins(app: &TestApp, email: &str, count: u32) -> u16 {
+    let mut status = 0;
+
+    for _ in 0..count {
+        status = login(app, email, "wrongpassword123")
+            .await
+            .status()
+            .as_u16();
+    }
+
+    status
+}
+
+#[tokio::test]
+async fn should_lock_account_after_repeated_failed_logins() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+
+    assert_eq!(
+        fail_logins(&app, &random_email, *LOGIN_LOCKOUT_THRESHOLD - 1).await,
+        401
+    );
+    assert_eq!(fail_logins(&app, &random_email, 1).await, 423);
+
+    // Even the right password is refused while the account is locked
+    let response = login(&app, &random_email, "password123").await;
+
+    assert_eq!(response.status().as_u16(), 423);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_unknown_emails_the_same_way() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    assert_eq!(
+        fail_logins(&app, &random_email, *LOGIN_LOCKOUT_THRESHOLD).await,
+        423
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn successful_login_should_reset_failures() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+
+    fail_logins(&app, &random_email, *LOGIN_LOCKOUT_THRESHOLD - 1).await;
+
+    let response = login(&app, &random_email, "password123").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(
+        fail_logins(&app, &random_email, *LOGIN_LOCKOUT_THRESHOLD - 1).await,
+        401
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_unlock_account_with_valid_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+
+    fail_logins(&app, &random_email, *LOGIN_LOCKOUT_THRESHOLD).await;
+
+    let token = generate_email_token(
+        &Email::parse(Secret::new(random_email.clone())).unwrap(),
+        EmailTokenPurpose::UnlockAccount,
+    )
+    .unwrap();
+
+    let response = app.post_unlock_account(token.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &random_email, "password123").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The link only works once
+    let response = app.post_unlock_account(token.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn opening_link_should_not_unlock_account() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+
+    fail_logins(&app, &random_email, *LOGIN_LOCKOUT_THRESHOLD).await;
+
+    let token = generate_email_token(
+        &Email::parse(Secret::new(random_email.clone())).unwrap(),
+        EmailTokenPurpose::UnlockAccount,
+    )
+    .unwrap();
+
+    // Link scanners only ever GET the link, which returns the page with the button
+    let response = app.get_unlock_account(token.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &random_email, "password123").await;
+
+    assert_eq!(response.status().as_u16(), 423);
+
+    let response = app.post_unlock_account(token.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup(&app).await;
+
+    let token = generate_email_token(
+        &Email::parse(Secret::new(random_email)).unwrap(),
+        EmailTokenPurpose::VerifyEmail,
+    )
+    .unwrap();
+
+    let response = app.post_unlock_account(token.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_unlock_account("invalid").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,202 @@
+use auth_service::{
+    routes::UserStatusResponse,
+    utils::constants::{ADMIN_CLIENTS, JWT_COOKIE_NAME},
+};
+use secrecy::ExposeSecret;
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn admin_credentials() -> (String, String) {
+    let (client_id, secret) = ADMIN_CLIENTS
+        .iter()
+        .next()
+        .expect("ADMIN_CLIENTS must be set to run these tests");
+
+    (client_id.to_owned(), secret.expose_secret().to_owned())
+}
+
+async fn signup_and_login(app: &TestApp) -> (String, String) {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (random_email, token)
+}
+
+#[tokio::test]
+async fn should_return_401_if_client_not_authenticated() {
+    let mut app = TestApp::new().await;
+
+    let (client_id, _) = admin_credentials();
+
+    let body = json!({
+        "email": get_random_email(),
+        "status": "suspended",
+        "reason": "Suspicious activity"
+    });
+
+    let test_cases = [None, Some((client_id.as_str(), "wrong-secret"))];
+
+    for credentials in test_cases {
+        let response = app.post_admin_user_status(&body, credentials).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    let (client_id, secret) = admin_credentials();
+    let (random_email, _) = signup_and_login(&app).await;
+
+    let test_cases = [
+        json!({ "email": "invalid", "status": "suspended", "reason": "Fraud" }),
+        json!({ "email": random_email, "status": "banned", "reason": "Fraud" }),
+        json!({ "email": random_email, "status": "suspended", "reason": "  " }),
+    ];
+
+    for test_case in test_cases {
+        let response = app
+            .post_admin_user_status(&test_case, Some((&client_id, &secret)))
+            .await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_user_not_found() {
+    let mut app = TestApp::new().await;
+
+    let (client_id, secret) = admin_credentials();
+
+    let response = app
+        .post_admin_user_status(
+            &json!({
+                "email": get_random_email(),
+                "status": "disabled",
+                "reason": "Fraud"
+            }),
+            Some((&client_id, &secret)),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn suspended_user_should_be_refused_and_lose_existing_tokens() {
+    let mut app = TestApp::new().await;
+
+    let (client_id, secret) = admin_credentials();
+    let (random_email, token) = signup_and_login(&app).await;
+
+    let response = app
+        .post_admin_user_status(
+            &json!({
+                "email": random_email,
+                "status": "suspended",
+                "reason": "Suspicious activity"
+            }),
+            Some((&client_id, &secret)),
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<UserStatusResponse>()
+        .await
+        .expect("Could not deserialize response body to UserStatusResponse");
+
+    assert_eq!(body.email, random_email);
+    assert_eq!(body.status, "suspended");
+    assert_eq!(body.reason.as_deref(), Some("Suspicious activity"));
+    assert!(body.changed_at.is_some());
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn reactivated_user_should_be_able_to_login() {
+    let mut app = TestApp::new().await;
+
+    let (client_id, secret) = admin_credentials();
+    let (random_email, _) = signup_and_login(&app).await;
+
+    for (status, reason) in [("disabled", "Requested by owner"), ("active", "Restored")] {
+        let response = app
+            .post_admin_user_status(
+                &json!({
+                    "email": random_email,
+                    "status": status,
+                    "reason": reason
+                }),
+                Some((&client_id, &secret)),
+            )
+            .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
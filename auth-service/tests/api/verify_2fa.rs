@@ -1,9 +1,12 @@
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{get_random_email, get_token_claims, TestApp};
 
 use auth_service::{
-    domain::{data_stores::LoginAttemptId, Email},
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode},
+        Email,
+    },
     routes::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    utils::constants::{JWT_COOKIE_NAME, MAX_2FA_ATTEMPTS},
 };
 use secrecy::{ExposeSecret, Secret};
 use serde_json::json;
@@ -105,52 +108,91 @@ async fn should_return_401_if_incorrect_credentials() {
     app.clean_up().await;
 }
 
-#[tokio::test]
-async fn should_return_401_if_old_code() {
-    let mut app = TestApp::new().await;
+async fn start_login(app: &TestApp, email: &str) -> (LoginAttemptId, TwoFACode) {
+    let response = app
+        .post_login(&json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
 
-    let random_email = get_random_email();
-    let password = "password123";
+    assert_eq!(response.status().as_u16(), 206);
 
-    let signup_body = serde_json::json!({
-        "email": random_email,
-        "password": password,
-        "requires2FA": true
-    });
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(login_attempt_id)).unwrap();
 
-    let response = app.post_signup(&signup_body).await;
+    let (_, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&login_attempt_id)
+        .await
+        .expect("No 2FA code stored");
+
+    (login_attempt_id, two_fa_code)
+}
+
+async fn signup_with_2fa(app: &TestApp) -> String {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": true
+        }))
+        .await;
 
     assert_eq!(response.status().as_u16(), 201);
 
-    let login_body = serde_json::json!({
-        "email": random_email,
-        "password": password,
-    });
+    random_email
+}
 
-    let response = app.post_login(&login_body).await;
+#[tokio::test]
+async fn should_accept_codes_from_concurrent_login_attempts() {
+    let mut app = TestApp::new().await;
 
-    let json_body = response
-        .json::<TwoFactorAuthResponse>()
-        .await
-        .expect("Could not deserialize response body to TwoFactorAuthResposne");
+    let random_email = signup_with_2fa(&app).await;
 
-    assert_eq!(json_body.message, "2FA required".to_owned());
+    // A login on another device must not cancel the one already waiting
+    let (laptop_attempt_id, laptop_code) = start_login(&app, &random_email).await;
+    let (phone_attempt_id, phone_code) = start_login(&app, &random_email).await;
 
-    let email = Email::parse(Secret::new(random_email.clone())).unwrap();
+    for (login_attempt_id, two_fa_code) in [
+        (laptop_attempt_id, laptop_code),
+        (phone_attempt_id, phone_code),
+    ] {
+        let response = app
+            .post_verify_2fa(&json!({
+                "email": random_email,
+                "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+                "2FACode": two_fa_code.as_ref().expose_secret()
+            }))
+            .await;
 
-    let result = app.two_fa_code_store.read().await.get_code(&email).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
 
-    if let Ok((login_attempt_id, two_fa_code)) = result {
-        assert_eq!(
-            json_body.login_attempt_id,
-            login_attempt_id.as_ref().expose_secret().to_owned()
-        );
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_login_attempt_belongs_to_another_user() {
+    let mut app = TestApp::new().await;
 
-        let _ = app.post_login(&login_body).await;
+    let victim_email = signup_with_2fa(&app).await;
+    let attacker_email = signup_with_2fa(&app).await;
 
+    let (login_attempt_id, two_fa_code) = start_login(&app, &victim_email).await;
+
+    for _ in 0..*MAX_2FA_ATTEMPTS {
         let response = app
             .post_verify_2fa(&json!({
-                "email": random_email,
+                "email": attacker_email,
                 "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
                 "2FACode": two_fa_code.as_ref().expose_secret()
             }))
@@ -159,6 +201,17 @@ async fn should_return_401_if_old_code() {
         assert_eq!(response.status().as_u16(), 401);
     }
 
+    // The mismatches did not count against the owner's attempt
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": victim_email,
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": two_fa_code.as_ref().expose_secret()
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
     app.clean_up().await;
 }
 
@@ -196,13 +249,18 @@ async fn should_return_200_if_correct_code() {
 
     let email = Email::parse(Secret::new(random_email.clone())).unwrap();
 
-    let result = app.two_fa_code_store.read().await.get_code(&email).await;
+    let login_attempt_id =
+        LoginAttemptId::parse(Secret::new(json_body.login_attempt_id.clone())).unwrap();
 
-    if let Ok((login_attempt_id, two_fa_code)) = result {
-        assert_eq!(
-            json_body.login_attempt_id,
-            login_attempt_id.as_ref().expose_secret().to_owned()
-        );
+    let result = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&login_attempt_id)
+        .await;
+
+    if let Ok((challenge_email, two_fa_code)) = result {
+        assert_eq!(challenge_email, email);
 
         let response = app
             .post_verify_2fa(&json!({
@@ -225,6 +283,69 @@ async fn should_return_200_if_correct_code() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_issue_token_for_audience_requested_at_login() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup_with_2fa(&app).await;
+
+    let response = app
+        .post_login(&json!({
+            "email": random_email,
+            "password": "password123",
+            "audience": "app-service"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let (_, two_fa_code) = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&LoginAttemptId::parse(Secret::new(login_attempt_id.clone())).unwrap())
+        .await
+        .expect("No 2FA code stored");
+
+    // A different audience at the second step is rejected
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+            "audience": "unknown-service"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Leaving it out keeps the one asked for at login
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret()
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert_eq!(get_token_claims(auth_cookie.value())["aud"], "app-service");
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_401_if_same_code_twice() {
     let mut app = TestApp::new().await;
@@ -259,13 +380,18 @@ async fn should_return_401_if_same_code_twice() {
 
     let email = Email::parse(Secret::new(random_email.clone())).unwrap();
 
-    let result = app.two_fa_code_store.read().await.get_code(&email).await;
+    let login_attempt_id =
+        LoginAttemptId::parse(Secret::new(json_body.login_attempt_id.clone())).unwrap();
 
-    if let Ok((login_attempt_id, two_fa_code)) = result {
-        assert_eq!(
-            json_body.login_attempt_id,
-            login_attempt_id.as_ref().expose_secret().to_owned()
-        );
+    let result = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&login_attempt_id)
+        .await;
+
+    if let Ok((challenge_email, two_fa_code)) = result {
+        assert_eq!(challenge_email, email);
 
         let response = app
             .post_verify_2fa(&json!({
@@ -297,3 +423,67 @@ async fn should_return_401_if_same_code_twice() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_return_429_after_too_many_incorrect_codes() {
+    let mut app = TestApp::new().await;
+
+    let random_email = signup_with_2fa(&app).await;
+
+    let (login_attempt_id, two_fa_code) = start_login(&app, &random_email).await;
+
+    let wrong_code = if two_fa_code.as_ref().expose_secret() == "123456" {
+        "654321"
+    } else {
+        "123456"
+    };
+
+    for attempt in 1..=*MAX_2FA_ATTEMPTS {
+        let response = app
+            .post_verify_2fa(&json!({
+                "email": random_email,
+                "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+                "2FACode": wrong_code
+            }))
+            .await;
+
+        let expected = if attempt < *MAX_2FA_ATTEMPTS {
+            401
+        } else {
+            429
+        };
+
+        assert_eq!(
+            response.status().as_u16(),
+            expected,
+            "Failed on attempt {}",
+            attempt
+        );
+    }
+
+    // The code was discarded along with the login attempt
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": two_fa_code.as_ref().expose_secret()
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Logging in again starts a fresh attempt with its own count
+    let (login_attempt_id, two_fa_code) = start_login(&app, &random_email).await;
+
+    let response = app
+        .post_verify_2fa(&json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+            "2FACode": two_fa_code.as_ref().expose_secret()
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,124 @@
+use auth_service::{
+    domain::Email,
+    routes::VerifyEmailResponse,
+    utils::email_token::{generate_email_token, EmailTokenPurpose},
+};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_422_if_token_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/verify-email", &app.address))
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_verify_email("invalid").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_user_does_not_exist() {
+    let mut app = TestApp::new().await;
+
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+    let token = generate_email_token(&email, EmailTokenPurpose::VerifyEmail).unwrap();
+
+    let response = app.post_verify_email(token.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_verify_email_only_once() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let email = Email::parse(Secret::new(random_email)).unwrap();
+    let token = generate_email_token(&email, EmailTokenPurpose::VerifyEmail).unwrap();
+
+    let response = app.post_verify_email(token.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let expected_response = VerifyEmailResponse {
+        message: "Email verified successfully!".to_owned(),
+    };
+
+    assert_eq!(
+        response
+            .json::<VerifyEmailResponse>()
+            .await
+            .expect("Could not deserialize response body to VerifyEmailResponse"),
+        expected_response
+    );
+
+    let response = app.post_verify_email(token.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn opening_link_should_not_use_up_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let email = Email::parse(Secret::new(random_email)).unwrap();
+    let token = generate_email_token(&email, EmailTokenPurpose::VerifyEmail).unwrap();
+
+    // Link scanners only ever GET the link, which returns the page with the button
+    let response = app.get_verify_email(token.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_email(token.expose_secret()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}